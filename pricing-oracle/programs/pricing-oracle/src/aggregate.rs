@@ -0,0 +1,99 @@
+use anchor_lang::prelude::*;
+
+use crate::signature::EvmAddress;
+use crate::OracleError;
+
+/// One publisher's validated price, or the aggregate of several.
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Quote {
+    pub price: i64,
+    pub conf: u64,
+    pub publish_time: i64,
+}
+
+/// The latest quote from one publisher of an aggregate feed.
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PublisherSlot {
+    pub publisher: [u8; 20],
+    pub quote: Quote,
+    /// Nanosecond timestamp of `quote`; the publisher's next quote must be newer.
+    pub timestamp_ns: u64,
+}
+
+/// Stores `quote` in `publisher`'s slot. Slots of publishers no longer in `publishers`
+/// are dropped first, so there is always room for an allowed one.
+pub fn record(
+    slots: &mut Vec<PublisherSlot>,
+    publishers: &[EvmAddress],
+    publisher: EvmAddress,
+    quote: Quote,
+    timestamp_ns: u64,
+) -> Result<()> {
+    slots.retain(|slot| publishers.contains(&slot.publisher));
+    match slots.iter_mut().find(|slot| slot.publisher == publisher) {
+        Some(slot) => {
+            require!(timestamp_ns > slot.timestamp_ns, OracleError::StaleUpdate);
+            slot.quote = quote;
+            slot.timestamp_ns = timestamp_ns;
+        }
+        None => slots.push(PublisherSlot {
+            publisher,
+            quote,
+            timestamp_ns,
+        }),
+    }
+    Ok(())
+}
+
+/// Quotes published at or after `oldest`.
+pub fn fresh(slots: &[PublisherSlot], oldest: i64) -> Vec<Quote> {
+    slots
+        .iter()
+        .map(|slot| slot.quote)
+        .filter(|quote| quote.publish_time >= oldest)
+        .collect()
+}
+
+/// Median of the sorted `prices`; the mean of the middle two for an even count.
+fn median(prices: &[i64]) -> i64 {
+    let mid = prices.len() / 2;
+    if prices.len() % 2 == 1 {
+        prices[mid]
+    } else {
+        ((i128::from(prices[mid - 1]) + i128::from(prices[mid])) / 2) as i64
+    }
+}
+
+/// Median price of `quotes` after dropping those more than `max_outlier_bps` from the
+/// median of all of them (0 keeps every quote). `None` if fewer than `min_publishers`
+/// quotes remain.
+///
+/// The confidence is the larger of the median publisher confidence and half the spread
+/// of the remaining prices, so disagreement between publishers widens it. The publish
+/// time is the newest of the remaining quotes.
+pub fn aggregate(mut quotes: Vec<Quote>, min_publishers: u8, max_outlier_bps: u32) -> Option<Quote> {
+    let quorum = usize::from(min_publishers.max(1));
+    if quotes.len() < quorum {
+        return None;
+    }
+    quotes.sort_by_key(|q| q.price);
+    if max_outlier_bps > 0 {
+        let prices: Vec<i64> = quotes.iter().map(|q| q.price).collect();
+        let first_pass = i128::from(median(&prices));
+        let limit = first_pass.abs() * i128::from(max_outlier_bps);
+        quotes.retain(|q| (i128::from(q.price) - first_pass).abs() * 10_000 <= limit);
+        if quotes.len() < quorum {
+            return None;
+        }
+    }
+
+    let prices: Vec<i64> = quotes.iter().map(|q| q.price).collect();
+    let mut confs: Vec<u64> = quotes.iter().map(|q| q.conf).collect();
+    confs.sort_unstable();
+    let spread = (i128::from(prices[prices.len() - 1]) - i128::from(prices[0])) / 2;
+    Some(Quote {
+        price: median(&prices),
+        conf: confs[confs.len() / 2].max(spread as u64),
+        publish_time: quotes.iter().map(|q| q.publish_time).max()?,
+    })
+}
@@ -1,6 +1,10 @@
+mod aggregate;
+mod signature;
 mod state;
 
-use crate::state::UpdateData;
+use crate::aggregate::{aggregate, fresh, record, PublisherSlot, Quote};
+use crate::signature::{recover_publisher, EvmAddress};
+use crate::state::{FeedMode, FeedParams, FeedStatus, SampleParams, SampledPrice, UpdateData};
 use anchor_lang::prelude::borsh::BorshSchema;
 use anchor_lang::prelude::*;
 use anchor_lang::require_keys_eq;
@@ -8,7 +12,6 @@ use core::mem::size_of;
 use ephemeral_rollups_sdk::anchor::{commit, delegate, ephemeral};
 use ephemeral_rollups_sdk::cpi::DelegateConfig;
 use ephemeral_rollups_sdk::ephem::commit_and_undelegate_accounts;
-use ephemeral_rollups_sdk::utils::close_pda;
 use pyth_solana_receiver_sdk::price_update::{PriceFeedMessage, PriceUpdateV2, VerificationLevel};
 use anchor_lang::prelude::instruction::Instruction;
 use anchor_lang::{InstructionData};
@@ -28,16 +31,38 @@ declare_id!("E61V4VY41AKGAqwwdbRhdJZ3cT8ou5DcW1M8Tqm9QdUj");
 
 #[cfg(not(feature = "test-mode"))] const ORACLE_IDENTITY: Pubkey = pubkey!("MPUxHCpNUy3K1CSVhebAmTbcTCKVxfk9YMDcUP2ZnEA");
 const SEED_PREFIX: &[u8] = b"price_feed";
+const FEED_CONFIG_SEED: &[u8] = b"feed_config";
+const PUBLISHER_SLOTS_SEED: &[u8] = b"publisher_slots";
+const REGISTRY_SEED: &[u8] = b"registry";
+pub const MAX_PUBLISHERS: usize = 8;
+/// Exponent of every price returned by `sample`.
+pub const SAMPLE_EXPONENT: i32 = -18;
 
 #[ephemeral]
 #[program]
 pub mod ephemeral_oracle {
     use super::*;
 
+    /// Creates the registry. `admin` is the only key that can create and manage feeds.
+    pub fn initialize_registry(ctx: Context<InitializeRegistry>, admin: Pubkey) -> Result<()> {
+        ensure_oracle(&ctx.accounts.payer)?;
+
+        let registry = &mut ctx.accounts.registry;
+        registry.admin = admin;
+        registry.bump = ctx.bumps.registry;
+        registry.feed_count = 0;
+        Ok(())
+    }
+
+    pub fn set_admin(ctx: Context<SetAdmin>, new_admin: Pubkey) -> Result<()> {
+        ctx.accounts.registry.admin = new_admin;
+        Ok(())
+    }
+
     pub fn initialize_price_feed(
         ctx: Context<InitializePriceFeed>,
-        _provider: String,
-        _symbol: String,
+        provider: String,
+        symbol: String,
         feed_id: [u8; 32],
         exponent: i32,
     ) -> Result<()> {
@@ -57,6 +82,101 @@ pub mod ephemeral_oracle {
             prev_publish_time: clock.unix_timestamp,
             publish_time: clock.unix_timestamp,
         };
+
+        let feed_config = &mut ctx.accounts.feed_config;
+        feed_config.price_feed = price_feed.key();
+        feed_config.bump = ctx.bumps.feed_config;
+        feed_config.publishers = Vec::new();
+        feed_config.params = FeedParams::default();
+        feed_config.provider = provider;
+        feed_config.symbol = symbol;
+        feed_config.status = FeedStatus::Active;
+        feed_config.created_at = clock.unix_timestamp;
+        ctx.accounts.registry.feed_count += 1;
+
+        let publisher_slots = &mut ctx.accounts.publisher_slots;
+        publisher_slots.price_feed = price_feed.key();
+        publisher_slots.bump = ctx.bumps.publisher_slots;
+        publisher_slots.slots = Vec::new();
+        publisher_slots.last_update_ns = 0;
+        Ok(())
+    }
+
+    /// Creates the `FeedConfig` and `PublisherSlots` of a feed initialized before they
+    /// existed. The feed starts active, with default params and no publishers. Such a feed
+    /// must be attached before `close_price_feed` can close it.
+    pub fn attach_feed_config(ctx: Context<AttachFeedConfig>, provider: String, symbol: String) -> Result<()> {
+        let price_feed = ctx.accounts.price_feed.key();
+
+        let feed_config = &mut ctx.accounts.feed_config;
+        feed_config.price_feed = price_feed;
+        feed_config.bump = ctx.bumps.feed_config;
+        feed_config.publishers = Vec::new();
+        feed_config.params = FeedParams::default();
+        feed_config.provider = provider;
+        feed_config.symbol = symbol;
+        feed_config.status = FeedStatus::Active;
+        feed_config.created_at = Clock::get()?.unix_timestamp;
+        ctx.accounts.registry.feed_count += 1;
+
+        let publisher_slots = &mut ctx.accounts.publisher_slots;
+        publisher_slots.price_feed = price_feed;
+        publisher_slots.bump = ctx.bumps.publisher_slots;
+        publisher_slots.slots = Vec::new();
+        publisher_slots.last_update_ns = 0;
+        Ok(())
+    }
+
+    pub fn set_feed_params(
+        ctx: Context<ConfigureFeed>,
+        _provider: String,
+        _symbol: String,
+        params: FeedParams,
+    ) -> Result<()> {
+        require!(params.ema_window > 0, OracleError::InvalidFeedParams);
+        if let FeedMode::Aggregate { min_publishers, .. } = params.mode {
+            require!(
+                (1..=MAX_PUBLISHERS).contains(&usize::from(min_publishers)),
+                OracleError::InvalidFeedParams
+            );
+        }
+        ctx.accounts.feed_config.params = params;
+        Ok(())
+    }
+
+    /// Replaces the publishers whose signatures `update_price_feed` accepts for this feed.
+    pub fn set_publishers(
+        ctx: Context<ConfigureFeed>,
+        _provider: String,
+        _symbol: String,
+        publishers: Vec<EvmAddress>,
+    ) -> Result<()> {
+        require!(publishers.len() <= MAX_PUBLISHERS, OracleError::TooManyPublishers);
+        ctx.accounts.feed_config.publishers = publishers;
+        Ok(())
+    }
+
+    pub fn pause_feed(ctx: Context<AdminFeed>, _provider: String, _symbol: String) -> Result<()> {
+        set_status(&mut ctx.accounts.feed_config, FeedStatus::Paused)
+    }
+
+    pub fn resume_feed(ctx: Context<AdminFeed>, _provider: String, _symbol: String) -> Result<()> {
+        set_status(&mut ctx.accounts.feed_config, FeedStatus::Active)
+    }
+
+    /// Permanently stops updates. The account stays readable until it is closed.
+    pub fn retire_feed(ctx: Context<AdminFeed>, _provider: String, _symbol: String) -> Result<()> {
+        set_status(&mut ctx.accounts.feed_config, FeedStatus::Retired)
+    }
+
+    /// Hands the feed's configuration rights to `new_authority`.
+    pub fn set_write_authority(
+        ctx: Context<SetWriteAuthority>,
+        _provider: String,
+        _symbol: String,
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.price_feed.write_authority = new_authority;
         Ok(())
     }
 
@@ -70,14 +190,94 @@ pub mod ephemeral_oracle {
         let clock = Clock::get()?;
         let price_feed = &mut ctx.accounts.price_feed;
 
-        let new_price: i64 = update_data.temporal_numeric_value.quantized_value as i64;
+        ensure_active(&ctx.accounts.feed_config)?;
+        require!(
+            update_data.id == price_feed.price_message.feed_id,
+            OracleError::FeedIdMismatch
+        );
+        let publisher = recover_publisher(&price_feed.key(), &update_data)?;
+        require!(
+            ctx.accounts.feed_config.publishers.contains(&publisher),
+            OracleError::UnknownPublisher
+        );
+
         let prev = price_feed.price_message;
+        require!(update_data.exponent == prev.exponent, OracleError::ExponentMismatch);
+        let price = i64::try_from(update_data.temporal_numeric_value.quantized_value)
+            .map_err(|_| error!(OracleError::PriceOverflow))?;
+        let params = ctx.accounts.feed_config.params;
+        let first = price_feed.posted_slot == 0;
+
+        // Publish times are whole seconds, so replays are caught on the nanosecond timestamp.
+        let timestamp_ns = update_data.temporal_numeric_value.timestamp_ns;
+        let publish_time = (timestamp_ns / 1_000_000_000) as i64;
+        let oldest = clock.unix_timestamp - i64::from(params.max_staleness_secs);
+        require!(publish_time >= oldest, OracleError::StaleUpdate);
+        require!(
+            publish_time <= clock.unix_timestamp + i64::from(params.max_future_secs),
+            OracleError::FutureUpdate
+        );
+        let quote = Quote {
+            price,
+            conf: update_data.conf,
+            publish_time,
+        };
+
+        let quote = match params.mode {
+            FeedMode::Single => {
+                let publisher_slots = &mut ctx.accounts.publisher_slots;
+                require!(
+                    publish_time >= prev.publish_time && timestamp_ns > publisher_slots.last_update_ns,
+                    OracleError::StaleUpdate
+                );
+                publisher_slots.last_update_ns = timestamp_ns;
+                quote
+            }
+            FeedMode::Aggregate {
+                min_publishers,
+                max_outlier_bps,
+            } => {
+                let slots = &mut ctx.accounts.publisher_slots.slots;
+                record(slots, &ctx.accounts.feed_config.publishers, publisher, quote, timestamp_ns)?;
+                let posted = Quote {
+                    price: prev.price,
+                    conf: prev.conf,
+                    publish_time: prev.publish_time,
+                };
+                // Without a quorum the quote only waits in its slot, and an unchanged
+                // aggregate is not posted again, so the EMA moves once per new price.
+                match aggregate(fresh(slots, oldest), min_publishers, max_outlier_bps) {
+                    Some(quote) if quote.publish_time >= prev.publish_time && quote != posted => quote,
+                    _ => return Ok(()),
+                }
+            }
+        };
+        let Quote {
+            price,
+            conf,
+            publish_time,
+        } = quote;
+        check_deviation(prev.price, price, params.max_deviation_bps)?;
+
+        // The first update seeds the averages instead of being averaged with zero.
+        let window = params.ema_window;
+        let (ema_price, ema_conf) = if first {
+            (price, conf)
+        } else {
+            (
+                ema(prev.ema_price.into(), price.into(), window) as i64,
+                ema(prev.ema_conf.into(), conf.into(), window) as u64,
+            )
+        };
 
         price_feed.posted_slot = clock.slot;
         price_feed.price_message = PriceFeedMessage {
             prev_publish_time: prev.publish_time,
-            price: new_price,
-            publish_time: clock.unix_timestamp,
+            price,
+            conf,
+            ema_price,
+            ema_conf,
+            publish_time,
             ..prev
         };
         price_feed.verification_level = VerificationLevel::Full;
@@ -97,6 +297,11 @@ pub mod ephemeral_oracle {
             &[SEED_PREFIX, provider.as_bytes(), symbol.as_bytes()],
             DelegateConfig::default(),
         )?;
+        ctx.accounts.delegate_publisher_slots(
+            &ctx.accounts.payer,
+            &[PUBLISHER_SLOTS_SEED, ctx.accounts.price_feed.key().as_ref()],
+            DelegateConfig::default(),
+        )?;
         Ok(())
     }
 
@@ -109,68 +314,123 @@ pub mod ephemeral_oracle {
 
         commit_and_undelegate_accounts(
             &ctx.accounts.payer,
-            vec![&ctx.accounts.price_feed.to_account_info()],
+            vec![
+                &ctx.accounts.price_feed.to_account_info(),
+                &ctx.accounts.publisher_slots.to_account_info(),
+            ],
             &ctx.accounts.magic_context,
             &ctx.accounts.magic_program,
         )?;
         Ok(())
     }
 
+    /// Closes the feed with its config and publisher slots, refunding the rent to the admin.
+    /// Both are required, so a feed initialized before they existed needs an
+    /// `attach_feed_config` call first.
     pub fn close_price_feed(
         ctx: Context<ClosePriceFeed>,
         _provider: String,
         _symbol: String,
     ) -> Result<()> {
-        ensure_oracle(&ctx.accounts.payer)?;
-        close_pda(
-            &ctx.accounts.price_feed,
-            &ctx.accounts.payer.to_account_info(),
-        )?;
+        let registry = &mut ctx.accounts.registry;
+        registry.feed_count = registry.feed_count.saturating_sub(1);
         Ok(())
     }
 
-    pub fn sample(ctx: Context<Sample>) -> Result<()> {
-        // Deserialize the price feed
-        let data_ref = ctx.accounts.price_update.data.borrow();
-        let price_update = PriceUpdateV2::try_deserialize_unchecked(&mut data_ref.as_ref())
-            .map_err(Into::<Error>::into)?;
-
-        // Reject if the update is older than 60 seconds
-        let maximum_age: u64 = 60;
-
-        // Feed id is the price_update account address
-        let feed_id: [u8; 32] = ctx.accounts.price_update.key().to_bytes();
-
-        let price = price_update.get_price_no_older_than(&Clock::get()?, maximum_age, &feed_id)?;
+    /// Reads a Pyth `PriceUpdateV2` or one of this program's `PriceUpdateV3` feeds and
+    /// returns its price as return data, so other programs can read it through CPI.
+    /// Our feeds must come with their `feed_config` and be active.
+    pub fn sample(ctx: Context<Sample>, params: SampleParams) -> Result<SampledPrice> {
+        let account = &ctx.accounts.price_update;
+        let data_ref = account.data.borrow();
+        // Both layouts are the same, so our feeds go through Pyth's checks too.
+        let price_update = if *account.owner == pyth_solana_receiver_sdk::ID {
+            PriceUpdateV2::try_deserialize(&mut data_ref.as_ref())?
+        } else if *account.owner == crate::ID {
+            let feed_config = ctx.accounts.feed_config.as_ref().ok_or(OracleError::MissingFeedConfig)?;
+            ensure_active(feed_config)?;
+            let feed = PriceUpdateV3::try_deserialize(&mut data_ref.as_ref())?;
+            PriceUpdateV2 {
+                write_authority: feed.write_authority,
+                verification_level: feed.verification_level,
+                price_message: feed.price_message,
+                posted_slot: feed.posted_slot,
+            }
+        } else {
+            return err!(OracleError::UnsupportedPriceAccount);
+        };
 
+        let price = price_update.get_price_no_older_than(&Clock::get()?, params.maximum_age, &params.feed_id)?;
+        if params.max_conf_bps > 0 {
+            require!(
+                u128::from(price.conf) * 10_000
+                    <= u128::from(price.price.unsigned_abs()) * u128::from(params.max_conf_bps),
+                OracleError::ConfidenceTooWide
+            );
+        }
+
+        let sampled = SampledPrice {
+            price: normalize(price.price.into(), price.exponent)?,
+            conf: normalize(price.conf.into(), price.exponent)? as u128,
+            exponent: SAMPLE_EXPONENT,
+            publish_time: price.publish_time,
+        };
         msg!(
-            "The price is ({} ± {}) * 10^-{}",
-            price.price,
-            price.conf,
-            price.exponent
-        );
-        msg!(
-            "The price is: {}",
-            price.price as f64 * 10_f64.powi(-price.exponent)
+            "The price is ({} ± {}) * 10^{}",
+            sampled.price,
+            sampled.conf,
+            SAMPLE_EXPONENT
         );
-        msg!("Slot: {}", price_update.posted_slot);
-        msg!("Message: {:?}", price_update.price_message);
-
-        Ok(())
+        Ok(sampled)
     }
-    pub fn schedule(ctx: Context<Schedule>,task_id: u16) -> Result<()> {
-         ctx.accounts.schedule(task_id, &ctx.bumps)
+    pub fn schedule(ctx: Context<Schedule>,task_id: u16, params: SampleParams) -> Result<()> {
+         ctx.accounts.schedule(task_id, params, &ctx.bumps)
      }
 
 }
 
 /* -------------------- Accounts -------------------- */
 
+#[derive(Accounts)]
+pub struct InitializeRegistry<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Registry::INIT_SPACE,
+        seeds = [REGISTRY_SEED],
+        bump
+    )]
+    pub registry: Account<'info, Registry>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetAdmin<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [REGISTRY_SEED],
+        bump = registry.bump,
+        has_one = admin @ OracleError::Unauthorized
+    )]
+    pub registry: Account<'info, Registry>,
+}
+
 #[derive(Accounts)]
 #[instruction(provider: String, symbol: String, feed_id: [u8; 32], exponent: i32)]
 pub struct InitializePriceFeed<'info> {
+    /// Must be the registry admin.
     #[account(mut)]
     pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [REGISTRY_SEED],
+        bump = registry.bump,
+        constraint = registry.admin == payer.key() @ OracleError::Unauthorized
+    )]
+    pub registry: Account<'info, Registry>,
     // Allocate for the actual V3 struct, not V2
     #[account(
         init,
@@ -180,9 +440,117 @@ pub struct InitializePriceFeed<'info> {
         bump
     )]
     pub price_feed: Account<'info, PriceUpdateV3>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + FeedConfig::INIT_SPACE,
+        seeds = [FEED_CONFIG_SEED, price_feed.key().as_ref()],
+        bump
+    )]
+    pub feed_config: Account<'info, FeedConfig>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PublisherSlots::INIT_SPACE,
+        seeds = [PUBLISHER_SLOTS_SEED, price_feed.key().as_ref()],
+        bump
+    )]
+    pub publisher_slots: Account<'info, PublisherSlots>,
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+#[instruction(provider: String, symbol: String)]
+pub struct AttachFeedConfig<'info> {
+    /// Must be the registry admin.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [REGISTRY_SEED],
+        bump = registry.bump,
+        constraint = registry.admin == payer.key() @ OracleError::Unauthorized
+    )]
+    pub registry: Account<'info, Registry>,
+    #[account(seeds = [SEED_PREFIX, provider.as_bytes(), symbol.as_bytes()], bump)]
+    pub price_feed: Account<'info, PriceUpdateV3>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + FeedConfig::INIT_SPACE,
+        seeds = [FEED_CONFIG_SEED, price_feed.key().as_ref()],
+        bump
+    )]
+    pub feed_config: Account<'info, FeedConfig>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PublisherSlots::INIT_SPACE,
+        seeds = [PUBLISHER_SLOTS_SEED, price_feed.key().as_ref()],
+        bump
+    )]
+    pub publisher_slots: Account<'info, PublisherSlots>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(provider: String, symbol: String)]
+pub struct ConfigureFeed<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [SEED_PREFIX, provider.as_bytes(), symbol.as_bytes()],
+        bump,
+        constraint = price_feed.write_authority == authority.key() @ OracleError::Unauthorized
+    )]
+    pub price_feed: Account<'info, PriceUpdateV3>,
+    #[account(
+        mut,
+        seeds = [FEED_CONFIG_SEED, price_feed.key().as_ref()],
+        bump = feed_config.bump
+    )]
+    pub feed_config: Account<'info, FeedConfig>,
+}
+
+/// Status changes only touch the config, so they work while the feed is delegated.
+#[derive(Accounts)]
+#[instruction(provider: String, symbol: String)]
+pub struct AdminFeed<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [REGISTRY_SEED],
+        bump = registry.bump,
+        has_one = admin @ OracleError::Unauthorized
+    )]
+    pub registry: Account<'info, Registry>,
+    /// CHECK: only its address is used
+    #[account(seeds = [SEED_PREFIX, provider.as_bytes(), symbol.as_bytes()], bump)]
+    pub price_feed: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [FEED_CONFIG_SEED, price_feed.key().as_ref()],
+        bump = feed_config.bump
+    )]
+    pub feed_config: Account<'info, FeedConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(provider: String, symbol: String)]
+pub struct SetWriteAuthority<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        seeds = [REGISTRY_SEED],
+        bump = registry.bump,
+        has_one = admin @ OracleError::Unauthorized
+    )]
+    pub registry: Account<'info, Registry>,
+    #[account(
+        mut,
+        seeds = [SEED_PREFIX, provider.as_bytes(), symbol.as_bytes()],
+        bump
+    )]
+    pub price_feed: Account<'info, PriceUpdateV3>,
+}
+
 #[derive(Accounts)]
 #[instruction(provider: String, update_data: UpdateData)]
 pub struct UpdatePriceFeed<'info> {
@@ -194,6 +562,17 @@ pub struct UpdatePriceFeed<'info> {
         bump
     )]
     pub price_feed: Account<'info, PriceUpdateV3>,
+    #[account(
+        seeds = [FEED_CONFIG_SEED, price_feed.key().as_ref()],
+        bump = feed_config.bump
+    )]
+    pub feed_config: Account<'info, FeedConfig>,
+    #[account(
+        mut,
+        seeds = [PUBLISHER_SLOTS_SEED, price_feed.key().as_ref()],
+        bump = publisher_slots.bump
+    )]
+    pub publisher_slots: Account<'info, PublisherSlots>,
 }
 
 #[delegate]
@@ -209,6 +588,14 @@ pub struct DelegatePriceFeed<'info> {
         bump
     )]
     pub price_feed: AccountInfo<'info>,
+    /// CHECK: delegated PDA
+    #[account(
+        mut,
+        del,
+        seeds = [PUBLISHER_SLOTS_SEED, price_feed.key().as_ref()],
+        bump
+    )]
+    pub publisher_slots: AccountInfo<'info>,
 }
 
 #[commit]
@@ -223,19 +610,49 @@ pub struct UndelegatePriceFeed<'info> {
         bump
     )]
     pub price_feed: AccountInfo<'info>,
+    /// CHECK: undelegated PDA
+    #[account(
+        mut,
+        seeds = [PUBLISHER_SLOTS_SEED, price_feed.key().as_ref()],
+        bump
+    )]
+    pub publisher_slots: AccountInfo<'info>,
 }
 
 #[derive(Accounts)]
 #[instruction(provider: String, symbol: String)]
 pub struct ClosePriceFeed<'info> {
+    /// Must be the registry admin.
+    #[account(mut)]
     pub payer: Signer<'info>,
-    /// CHECK: PDA to close
     #[account(
         mut,
+        seeds = [REGISTRY_SEED],
+        bump = registry.bump,
+        constraint = registry.admin == payer.key() @ OracleError::Unauthorized
+    )]
+    pub registry: Account<'info, Registry>,
+    #[account(
+        mut,
+        close = payer,
         seeds = [SEED_PREFIX, provider.as_bytes(), symbol.as_bytes()],
         bump
     )]
-    pub price_feed: AccountInfo<'info>,
+    pub price_feed: Account<'info, PriceUpdateV3>,
+    #[account(
+        mut,
+        close = payer,
+        seeds = [FEED_CONFIG_SEED, price_feed.key().as_ref()],
+        bump = feed_config.bump
+    )]
+    pub feed_config: Account<'info, FeedConfig>,
+    #[account(
+        mut,
+        close = payer,
+        seeds = [PUBLISHER_SLOTS_SEED, price_feed.key().as_ref()],
+        bump = publisher_slots.bump
+    )]
+    pub publisher_slots: Account<'info, PublisherSlots>,
 }
 
 #[derive(Accounts)]
@@ -243,8 +660,11 @@ pub struct Sample<'info> {
      /// CHECK: For tuk tuk intractions 
     #[account(mut)]
     pub payer: UncheckedAccount<'info>,
-    /// CHECK: external price update account
+    /// CHECK: owner and layout are checked in `sample`
     pub price_update: AccountInfo<'info>,
+    /// Required when `price_update` is one of this program's feeds.
+    #[account(seeds = [FEED_CONFIG_SEED, price_update.key().as_ref()], bump = feed_config.bump)]
+    pub feed_config: Option<Account<'info, FeedConfig>>,
 }
 
 /* -------------------- State -------------------- */
@@ -258,8 +678,103 @@ pub struct PriceUpdateV3 {
     pub posted_slot: u64,
 }
 
+/// Program-wide settings. There is no on-chain list of feeds: clients enumerate them
+/// with `getProgramAccounts` filtered on the `FeedConfig` discriminator
+/// (`program.account.feedConfig.all()` in Anchor).
+#[account]
+#[derive(InitSpace)]
+pub struct Registry {
+    pub admin: Pubkey,
+    pub bump: u8,
+    /// Feeds with a `FeedConfig`, retired ones included. Closing a feed decrements it.
+    pub feed_count: u64,
+}
+
+/// Per-feed settings kept out of `PriceUpdateV3` so that account stays Pyth-compatible.
+#[account]
+#[derive(InitSpace)]
+pub struct FeedConfig {
+    pub price_feed: Pubkey,
+    pub bump: u8,
+    /// Addresses allowed to sign updates. Empty rejects every update.
+    #[max_len(MAX_PUBLISHERS)]
+    pub publishers: Vec<[u8; 20]>,
+    pub params: FeedParams,
+    // Seeds are at most 32 bytes, which bounds the names.
+    #[max_len(32)]
+    pub provider: String,
+    #[max_len(32)]
+    pub symbol: String,
+    pub status: FeedStatus,
+    pub created_at: i64,
+}
+
+/// Latest quote per publisher of an aggregate feed. Delegated together with the price
+/// feed, since every update writes it.
+#[account]
+#[derive(InitSpace)]
+pub struct PublisherSlots {
+    pub price_feed: Pubkey,
+    pub bump: u8,
+    #[max_len(MAX_PUBLISHERS)]
+    pub slots: Vec<PublisherSlot>,
+    /// `timestamp_ns` of the last single-mode update; later ones must be newer.
+    pub last_update_ns: u64,
+}
+
 /* -------------------- Helpers & Errors -------------------- */
 
+/// One EMA step with smoothing factor `2 / (window + 1)`. The result lies between
+/// `prev` and `sample`, so it fits whichever integer type both came from.
+fn ema(prev: i128, sample: i128, window: u32) -> i128 {
+    let n = i128::from(window) + 1;
+    (prev * (n - 2) + sample * 2) / n
+}
+
+/// Circuit breaker: rejects a move from `prev` to `price` of more than `max_bps` basis
+/// points. A zero `prev` means no price has been posted yet, so anything goes.
+fn check_deviation(prev: i64, price: i64, max_bps: u32) -> Result<()> {
+    if max_bps == 0 || prev == 0 {
+        return Ok(());
+    }
+    let moved = (i128::from(price) - i128::from(prev)).abs() * 10_000;
+    require!(
+        moved <= i128::from(prev).abs() * i128::from(max_bps),
+        OracleError::DeviationTooLarge
+    );
+    Ok(())
+}
+
+fn ensure_active(feed_config: &FeedConfig) -> Result<()> {
+    match feed_config.status {
+        FeedStatus::Active => Ok(()),
+        FeedStatus::Paused => err!(OracleError::FeedPaused),
+        FeedStatus::Retired => err!(OracleError::FeedRetired),
+    }
+}
+
+/// Retired feeds stay retired.
+fn set_status(feed_config: &mut FeedConfig, status: FeedStatus) -> Result<()> {
+    require!(feed_config.status != FeedStatus::Retired, OracleError::FeedRetired);
+    feed_config.status = status;
+    Ok(())
+}
+
+/// `value * 10^exponent` rescaled to `SAMPLE_EXPONENT`, rounding toward zero.
+fn normalize(value: i128, exponent: i32) -> Result<i128> {
+    let shift = exponent
+        .checked_sub(SAMPLE_EXPONENT)
+        .ok_or_else(|| error!(OracleError::PriceOverflow))?;
+    let scale = 10i128
+        .checked_pow(shift.unsigned_abs())
+        .ok_or_else(|| error!(OracleError::PriceOverflow))?;
+    if shift >= 0 {
+        value.checked_mul(scale).ok_or_else(|| error!(OracleError::PriceOverflow))
+    } else {
+        Ok(value / scale)
+    }
+}
+
 fn ensure_oracle(payer: &Signer) -> Result<()> {
     #[cfg(not(feature = "test-mode"))]
     require_keys_eq!(payer.key(), ORACLE_IDENTITY, OracleError::Unauthorized);
@@ -270,16 +785,48 @@ fn ensure_oracle(payer: &Signer) -> Result<()> {
 pub enum OracleError {
     #[msg("Unauthorized")]
     Unauthorized,
+    #[msg("Update signature is malformed or does not recover")]
+    InvalidSignature,
+    #[msg("Update is not signed by an allowed publisher of this feed")]
+    UnknownPublisher,
+    #[msg("Update id does not match the feed id")]
+    FeedIdMismatch,
+    #[msg("Too many publishers")]
+    TooManyPublishers,
+    #[msg("Update exponent does not match the feed exponent")]
+    ExponentMismatch,
+    #[msg("Price does not fit in 64 bits")]
+    PriceOverflow,
+    #[msg("Invalid feed parameters")]
+    InvalidFeedParams,
+    #[msg("Update is older than the feed's last publish time or the staleness limit")]
+    StaleUpdate,
+    #[msg("Update timestamp is too far in the future")]
+    FutureUpdate,
+    #[msg("Price moved more than the feed's maximum deviation")]
+    DeviationTooLarge,
+    #[msg("Feed is paused")]
+    FeedPaused,
+    #[msg("Feed is retired")]
+    FeedRetired,
+    #[msg("Price account is neither a Pyth nor an oracle price update")]
+    UnsupportedPriceAccount,
+    #[msg("Confidence is wider than the requested ratio")]
+    ConfidenceTooWide,
+    #[msg("Oracle feeds must be sampled with their feed config")]
+    MissingFeedConfig,
 }
 
 
 #[derive(Accounts)]
-#[instruction(user_pubkey: Pubkey, user_data: String,task_id: u16, seed: u64)]
+#[instruction(task_id: u16, params: SampleParams)]
 pub struct Schedule<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
     /// CHECK: external price update account
     pub price_update: AccountInfo<'info>,
+    /// CHECK: passed through to `sample`, which checks it when the task runs
+    pub feed_config: Option<UncheckedAccount<'info>>,
 
     pub system_program: Program<'info, System>,
    
@@ -310,14 +857,15 @@ pub struct Schedule<'info> {
 }
 
 impl<'info> Schedule<'info> {
-    pub fn schedule(&mut self,  task_id: u16, bump: &ScheduleBumps) -> Result<()> {
+    pub fn schedule(&mut self,  task_id: u16, params: SampleParams, bump: &ScheduleBumps) -> Result<()> {
       
             let (compiled_tx, _) = compile_transaction(
                 vec![Instruction {
                     program_id: crate::ID,
                     accounts: crate::__cpi_client_accounts_sample::Sample {
                        payer:self.payer.to_account_info(),
-                       price_update:self.price_update.to_account_info()
+                       price_update:self.price_update.to_account_info(),
+                       feed_config: self.feed_config.as_ref().map(|a| a.to_account_info())
                        
                        
                        
@@ -327,7 +875,7 @@ impl<'info> Schedule<'info> {
                     .to_account_metas(Some(true))
                     .to_vec(),
                     data: crate::instruction::Sample{
-
+                        params
                     }.data()
                 }],
                 vec![],
@@ -0,0 +1,66 @@
+use anchor_lang::prelude::*;
+use solana_keccak_hasher::hashv;
+use solana_secp256k1_recover::secp256k1_recover;
+
+use crate::state::UpdateData;
+use crate::OracleError;
+
+/// Ethereum-style publisher address: the last 20 bytes of keccak256 of the public key.
+pub type EvmAddress = [u8; 20];
+
+/// Half the secp256k1 group order. Signatures with a larger `s` are the malleated
+/// twin of a valid one and are rejected.
+const HALF_ORDER: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
+/// 32-byte big-endian two's complement word, like Solidity's `int256` / `uint256`.
+fn word(value: i128) -> [u8; 32] {
+    let mut word = [if value < 0 { 0xff } else { 0 }; 32];
+    word[16..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+/// keccak256(price_feed ‖ id ‖ timestamp_ns ‖ quantized_value ‖ conf ‖ exponent ‖
+/// publisher_merkle_root ‖ value_compute_alg_hash), with the integers as 32-byte words,
+/// like Solidity's `abi.encodePacked(bytes32, bytes32, uint256, int256, uint256, int256,
+/// bytes32, bytes32)`. The feed account binds the signature to one feed, so it cannot be
+/// replayed on another feed that shares the id.
+pub fn update_hash(price_feed: &Pubkey, update: &UpdateData) -> [u8; 32] {
+    let value = &update.temporal_numeric_value;
+    hashv(&[
+        price_feed.as_ref(),
+        &update.id,
+        &word(value.timestamp_ns.into()),
+        &word(value.quantized_value),
+        &word(update.conf.into()),
+        &word(update.exponent.into()),
+        &update.publisher_merkle_root,
+        &update.value_compute_alg_hash,
+    ])
+    .to_bytes()
+}
+
+/// Recovers the address that signed `update` for `price_feed` with `personal_sign`
+/// (`"\x19Ethereum Signed Message:\n32" ‖ update_hash`).
+pub fn recover_publisher(price_feed: &Pubkey, update: &UpdateData) -> Result<EvmAddress> {
+    let digest = hashv(&[b"\x19Ethereum Signed Message:\n32", &update_hash(price_feed, update)]).to_bytes();
+    let recovery_id = match update.v {
+        0 | 1 => update.v,
+        27 | 28 => update.v - 27,
+        _ => return err!(OracleError::InvalidSignature),
+    };
+    require!(update.s <= HALF_ORDER, OracleError::InvalidSignature);
+
+    let mut signature = [0u8; 64];
+    signature[..32].copy_from_slice(&update.r);
+    signature[32..].copy_from_slice(&update.s);
+    let public_key = secp256k1_recover(&digest, recovery_id, &signature)
+        .map_err(|_| error!(OracleError::InvalidSignature))?;
+
+    let hash = hashv(&[&public_key.to_bytes()]).to_bytes();
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..]);
+    Ok(address)
+}
@@ -11,9 +11,79 @@ pub struct UpdateData {
     pub symbol: String,
     pub id: [u8; 32],
     pub temporal_numeric_value: TemporalNumericValue,
+    /// Confidence interval around `quantized_value`, in the same units.
+    pub conf: u64,
+    /// Must equal the feed's exponent.
+    pub exponent: i32,
     pub publisher_merkle_root: [u8; 32],
     pub value_compute_alg_hash: [u8; 32],
     pub r: [u8; 32],
     pub s: [u8; 32],
     pub v: u8,
-}
\ No newline at end of file
+}
+
+/// Per-feed tuning, set with `set_feed_params`.
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FeedParams {
+    /// Number of updates the EMA averages over (smoothing factor `2 / (ema_window + 1)`).
+    pub ema_window: u32,
+    /// Oldest publisher timestamp accepted, in seconds behind the cluster clock.
+    pub max_staleness_secs: u32,
+    /// Newest publisher timestamp accepted, in seconds ahead of the cluster clock.
+    pub max_future_secs: u32,
+    /// Largest move from the previous price, in basis points. 0 turns the check off.
+    pub max_deviation_bps: u32,
+    pub mode: FeedMode,
+}
+
+impl Default for FeedParams {
+    fn default() -> Self {
+        FeedParams {
+            ema_window: 30,
+            max_staleness_secs: 60,
+            max_future_secs: 5,
+            max_deviation_bps: 0,
+            mode: FeedMode::Single,
+        }
+    }
+}
+
+/// How publisher updates become the feed price.
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FeedMode {
+    /// Each accepted update is the new price.
+    Single,
+    /// Each update fills its publisher's slot, and the price is the median of the slots
+    /// published within `max_staleness_secs`. Nothing is posted until `min_publishers`
+    /// of them are within `max_outlier_bps` of the median (0 keeps every slot).
+    Aggregate { min_publishers: u8, max_outlier_bps: u32 },
+}
+
+/// Lifecycle of a feed, set by the registry admin.
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FeedStatus {
+    Active,
+    /// Updates are rejected until the feed is resumed.
+    Paused,
+    /// Updates are rejected for good.
+    Retired,
+}
+
+/// Caller limits for `sample`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SampleParams {
+    pub feed_id: [u8; 32],
+    /// Oldest publish time accepted, in seconds behind the cluster clock.
+    pub maximum_age: u64,
+    /// Widest confidence accepted, in basis points of the price. 0 turns the check off.
+    pub max_conf_bps: u32,
+}
+
+/// A price returned by `sample`, scaled to `SAMPLE_EXPONENT` whatever the source exponent.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SampledPrice {
+    pub price: i128,
+    pub conf: u128,
+    pub exponent: i32,
+    pub publish_time: i64,
+}
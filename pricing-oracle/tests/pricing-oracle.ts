@@ -8,6 +8,8 @@ import {
   LAMPORTS_PER_SOL,
 } from "@solana/web3.js";
 import { assert } from "chai";
+import { secp256k1 } from "@noble/curves/secp256k1";
+import { keccak_256 } from "@noble/hashes/sha3";
 import {
   init,
   taskKey,
@@ -28,6 +30,16 @@ describe("pricing-oracle", () => {
   const SYMBOL = "BTC/USD";
   const FEED_ID = Array(32).fill(1);
   const EXPONENT = -8;
+  const PUBLISHER_KEY = Uint8Array.from(Array(32).fill(7));
+  const OTHER_KEY = Uint8Array.from(Array(32).fill(8));
+  const THIRD_KEY = Uint8Array.from(Array(32).fill(9));
+  const FEED_PARAMS = {
+    emaWindow: 3,
+    maxStalenessSecs: 60,
+    maxFutureSecs: 5,
+    maxDeviationBps: 0,
+    mode: { single: {} },
+  };
 
   // Generate test accounts
   let priceFeedPda: PublicKey;
@@ -139,6 +151,101 @@ describe("pricing-oracle", () => {
     }
   }
 
+  // ---------- Helpers for signed updates ----------
+
+  function evmAddress(secretKey: Uint8Array): number[] {
+    const publicKey = secp256k1.getPublicKey(secretKey, false).slice(1);
+    return Array.from(keccak_256(publicKey).slice(12));
+  }
+
+  // 32-byte big-endian two's complement word, like Solidity's uint256 / int256.
+  function word(value: bigint): Buffer {
+    return Buffer.from(
+      BigInt.asUintN(256, value).toString(16).padStart(64, "0"),
+      "hex"
+    );
+  }
+
+  function signedUpdate(
+    secretKey: Uint8Array,
+    timestampNs: bigint,
+    quantizedValue: bigint,
+    conf = 0n,
+    exponent = EXPONENT,
+    priceFeed = priceFeedPda
+  ) {
+    const update = {
+      symbol: SYMBOL,
+      id: FEED_ID,
+      temporal_numeric_value: {
+        timestamp_ns: new anchor.BN(timestampNs.toString()),
+        quantized_value: new anchor.BN(quantizedValue.toString()),
+      },
+      conf: new anchor.BN(conf.toString()),
+      exponent,
+      publisher_merkle_root: Array(32).fill(0),
+      value_compute_alg_hash: Array(32).fill(0),
+    };
+    const hash = keccak_256(
+      Buffer.concat([
+        priceFeed.toBuffer(),
+        Buffer.from(update.id),
+        word(timestampNs),
+        word(quantizedValue),
+        word(conf),
+        word(BigInt(exponent)),
+        Buffer.from(update.publisher_merkle_root),
+        Buffer.from(update.value_compute_alg_hash),
+      ])
+    );
+    const digest = keccak_256(
+      Buffer.concat([Buffer.from("\x19Ethereum Signed Message:\n32"), hash])
+    );
+    const signature = secp256k1.sign(digest, secretKey);
+    return {
+      ...update,
+      r: Array.from(word(signature.r)),
+      s: Array.from(word(signature.s)),
+      v: 27 + signature.recovery,
+    };
+  }
+
+  // ---------- Registry tests ----------
+
+  const [registryPda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("registry")],
+    program.programId
+  );
+
+  it("Initialize registry", async () => {
+    if (!(await program.account.registry.fetchNullable(registryPda))) {
+      await program.methods
+        .initializeRegistry(provider.wallet.publicKey)
+        .accounts({ payer: provider.wallet.publicKey })
+        .rpc();
+    }
+    const registry = await program.account.registry.fetch(registryPda);
+    assert.equal(
+      registry.admin.toString(),
+      provider.wallet.publicKey.toString()
+    );
+  });
+
+  it("Only the admin creates feeds", async () => {
+    const stranger = anchor.web3.Keypair.generate();
+    await fundWithTransfer(stranger.publicKey, LAMPORTS_PER_SOL / 10);
+    try {
+      await program.methods
+        .initializePriceFeed(PROVIDER, "SOL/USD", Array(32).fill(3), EXPONENT)
+        .accounts({ payer: stranger.publicKey })
+        .signers([stranger])
+        .rpc();
+      assert.fail("a non-admin created a feed");
+    } catch (error) {
+      assert.include(error.toString(), "Unauthorized");
+    }
+  });
+
   // ---------- Price feed tests ----------
 
   it("Initialize price feed", async () => {
@@ -176,24 +283,33 @@ describe("pricing-oracle", () => {
     console.log("Price feed initialized successfully");
   });
 
+  it("Set feed publishers", async () => {
+    await program.methods
+      .setPublishers(PROVIDER, SYMBOL, [evmAddress(PUBLISHER_KEY)])
+      .accounts({
+        authority: provider.wallet.publicKey,
+        price_feed: priceFeedPda,
+      })
+      .rpc();
+
+    const [feedConfigPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("feed_config"), priceFeedPda.toBuffer()],
+      program.programId
+    );
+    const feedConfig = await program.account.feedConfig.fetch(feedConfigPda);
+    assert.deepEqual(feedConfig.publishers, [evmAddress(PUBLISHER_KEY)]);
+  });
+
   it("Update price feed", async () => {
-    // Create test update data
     const timestamp_ns = BigInt(Date.now()) * 1000000n; // Convert to nanoseconds using BigInt
     const quantized_value = new anchor.BN(50000000000); // 5.00 * 10^8 (since exponent is -8)
-
-    const updateData = {
-      symbol: SYMBOL,
-      id: FEED_ID,
-      temporal_numeric_value: {
-        timestamp_ns: new anchor.BN(timestamp_ns.toString()),
-        quantized_value: quantized_value,
-      },
-      publisher_merkle_root: Array(32).fill(0),
-      value_compute_alg_hash: Array(32).fill(0),
-      r: Array(32).fill(0),
-      s: Array(32).fill(0),
-      v: 27,
-    };
+    const conf = 25000000n; // 0.25
+    const updateData = signedUpdate(
+      PUBLISHER_KEY,
+      timestamp_ns,
+      BigInt(quantized_value.toString()),
+      conf
+    );
 
     const tx = await program.methods
       .updatePriceFeed(PROVIDER, updateData)
@@ -219,9 +335,247 @@ describe("pricing-oracle", () => {
       priceFeedAccount.postedSlot.toNumber() > 0,
       "Posted slot should be greater than 0"
     );
+    assert.equal(priceFeedAccount.priceMessage.conf.toString(), conf.toString());
+    // The first update seeds the EMA.
+    assert.equal(
+      priceFeedAccount.priceMessage.emaPrice.toString(),
+      quantized_value.toString()
+    );
     console.log("Price feed updated successfully");
   });
 
+  it("Averages later updates into the EMA", async () => {
+    await program.methods
+      .setFeedParams(PROVIDER, SYMBOL, FEED_PARAMS)
+      .accounts({
+        authority: provider.wallet.publicKey,
+        price_feed: priceFeedPda,
+      })
+      .rpc();
+
+    // Window 3 weighs the new sample by 2 / (3 + 1).
+    const timestamp_ns = BigInt(Date.now()) * 1000000n;
+    await program.methods
+      .updatePriceFeed(
+        PROVIDER,
+        signedUpdate(PUBLISHER_KEY, timestamp_ns, 52000000000n, 25000000n)
+      )
+      .accounts({
+        payer: provider.wallet.publicKey,
+        price_feed: priceFeedPda,
+      })
+      .rpc();
+    let priceFeedAccount = await program.account.priceUpdateV3.fetch(
+      priceFeedPda
+    );
+    assert.equal(priceFeedAccount.priceMessage.emaPrice.toString(), "51000000000");
+
+    // Another update with the same timestamp is a replay and does not move the EMA.
+    await expectRejected(
+      signedUpdate(PUBLISHER_KEY, timestamp_ns, 53000000000n, 25000000n),
+      "StaleUpdate"
+    );
+    priceFeedAccount = await program.account.priceUpdateV3.fetch(priceFeedPda);
+    assert.equal(priceFeedAccount.priceMessage.emaPrice.toString(), "51000000000");
+  });
+
+  async function expectRejected(updateData: any, code: string) {
+    try {
+      await program.methods
+        .updatePriceFeed(PROVIDER, updateData)
+        .accounts({
+          payer: provider.wallet.publicKey,
+          price_feed: priceFeedPda,
+        })
+        .rpc();
+      assert.fail(`update was accepted, expected ${code}`);
+    } catch (error) {
+      assert.include(error.toString(), code);
+    }
+  }
+
+  it("Rejects stale and future updates", async () => {
+    const now = BigInt(Date.now()) * 1000000n;
+    const second = 1000000000n;
+    await expectRejected(
+      signedUpdate(PUBLISHER_KEY, now - 120n * second, 52000000000n),
+      "StaleUpdate"
+    );
+    await expectRejected(
+      signedUpdate(PUBLISHER_KEY, now + 60n * second, 52000000000n),
+      "FutureUpdate"
+    );
+  });
+
+  it("Circuit breaker rejects large moves", async () => {
+    await program.methods
+      .setFeedParams(PROVIDER, SYMBOL, { ...FEED_PARAMS, maxDeviationBps: 500 })
+      .accounts({
+        authority: provider.wallet.publicKey,
+        price_feed: priceFeedPda,
+      })
+      .rpc();
+    const now = BigInt(Date.now()) * 1000000n;
+    // 52 -> 78 is a 50% move against a 5% limit.
+    await expectRejected(
+      signedUpdate(PUBLISHER_KEY, now, 78000000000n),
+      "DeviationTooLarge"
+    );
+    await program.methods
+      .setFeedParams(PROVIDER, SYMBOL, FEED_PARAMS)
+      .accounts({
+        authority: provider.wallet.publicKey,
+        price_feed: priceFeedPda,
+      })
+      .rpc();
+  });
+
+  it("Rejects updates with another exponent", async () => {
+    const timestamp_ns = BigInt(Date.now()) * 1000000n;
+    try {
+      await program.methods
+        .updatePriceFeed(
+          PROVIDER,
+          signedUpdate(PUBLISHER_KEY, timestamp_ns, 5n, 0n, -6)
+        )
+        .accounts({
+          payer: provider.wallet.publicKey,
+          price_feed: priceFeedPda,
+        })
+        .rpc();
+      assert.fail("update with a different exponent was accepted");
+    } catch (error) {
+      assert.include(error.toString(), "ExponentMismatch");
+    }
+  });
+
+  it("Rejects updates from unknown publishers", async () => {
+    const timestamp_ns = BigInt(Date.now()) * 1000000n;
+    const updateData = signedUpdate(OTHER_KEY, timestamp_ns, 1n);
+    try {
+      await program.methods
+        .updatePriceFeed(PROVIDER, updateData)
+        .accounts({
+          payer: provider.wallet.publicKey,
+          price_feed: priceFeedPda,
+        })
+        .rpc();
+      assert.fail("update from an unknown publisher was accepted");
+    } catch (error) {
+      assert.include(error.toString(), "UnknownPublisher");
+    }
+  });
+
+  it("Paused feeds reject updates", async () => {
+    await program.methods
+      .pauseFeed(PROVIDER, SYMBOL)
+      .accounts({ admin: provider.wallet.publicKey, price_feed: priceFeedPda })
+      .rpc();
+    const now = BigInt(Date.now()) * 1000000n;
+    await expectRejected(
+      signedUpdate(PUBLISHER_KEY, now, 52000000000n),
+      "FeedPaused"
+    );
+    await program.methods
+      .resumeFeed(PROVIDER, SYMBOL)
+      .accounts({ admin: provider.wallet.publicKey, price_feed: priceFeedPda })
+      .rpc();
+  });
+
+  it("Admin rotates the write authority", async () => {
+    const next = anchor.web3.Keypair.generate().publicKey;
+    const rotate = (authority: PublicKey) =>
+      program.methods
+        .setWriteAuthority(PROVIDER, SYMBOL, authority)
+        .accounts({ admin: provider.wallet.publicKey, price_feed: priceFeedPda })
+        .rpc();
+
+    await rotate(next);
+    let priceFeedAccount = await program.account.priceUpdateV3.fetch(
+      priceFeedPda
+    );
+    assert.equal(priceFeedAccount.writeAuthority.toString(), next.toString());
+    try {
+      await program.methods
+        .setFeedParams(PROVIDER, SYMBOL, FEED_PARAMS)
+        .accounts({
+          authority: provider.wallet.publicKey,
+          price_feed: priceFeedPda,
+        })
+        .rpc();
+      assert.fail("the previous write authority configured the feed");
+    } catch (error) {
+      assert.include(error.toString(), "Unauthorized");
+    }
+
+    await rotate(provider.wallet.publicKey);
+    priceFeedAccount = await program.account.priceUpdateV3.fetch(priceFeedPda);
+    assert.equal(
+      priceFeedAccount.writeAuthority.toString(),
+      provider.wallet.publicKey.toString()
+    );
+  });
+
+  it("Aggregates several publishers", async () => {
+    const configure = async (publishers: Uint8Array[], params: any) => {
+      await program.methods
+        .setPublishers(PROVIDER, SYMBOL, publishers.map(evmAddress))
+        .accounts({
+          authority: provider.wallet.publicKey,
+          price_feed: priceFeedPda,
+        })
+        .rpc();
+      await program.methods
+        .setFeedParams(PROVIDER, SYMBOL, params)
+        .accounts({
+          authority: provider.wallet.publicKey,
+          price_feed: priceFeedPda,
+        })
+        .rpc();
+    };
+    const publish = async (secretKey: Uint8Array, price: bigint) => {
+      const now = BigInt(Date.now()) * 1000000n;
+      await program.methods
+        .updatePriceFeed(PROVIDER, signedUpdate(secretKey, now, price))
+        .accounts({
+          payer: provider.wallet.publicKey,
+          price_feed: priceFeedPda,
+        })
+        .rpc();
+      return (await program.account.priceUpdateV3.fetch(priceFeedPda))
+        .priceMessage;
+    };
+
+    await configure([PUBLISHER_KEY, OTHER_KEY, THIRD_KEY], {
+      ...FEED_PARAMS,
+      mode: { aggregate: { minPublishers: 2, maxOutlierBps: 1000 } },
+    });
+    const before = (await program.account.priceUpdateV3.fetch(priceFeedPda))
+      .priceMessage;
+
+    // One quote is below the quorum and only fills its slot.
+    let message = await publish(PUBLISHER_KEY, 60000000000n);
+    assert.equal(message.price.toString(), before.price.toString());
+
+    // Median of 60 and 61; the spread sets the confidence.
+    message = await publish(OTHER_KEY, 61000000000n);
+    assert.equal(message.price.toString(), "60500000000");
+    assert.equal(message.conf.toString(), "500000000");
+
+    // 90 is more than 10% from the median of 60, 61 and 90, so it is dropped. The
+    // aggregate is unchanged, so the EMA does not move either.
+    const ema = message.emaPrice.toString();
+    message = await publish(THIRD_KEY, 90000000000n);
+    assert.equal(message.price.toString(), "60500000000");
+    assert.equal(message.emaPrice.toString(), ema);
+
+    // Each publisher's quotes must be newer than its last one.
+    const earlier = BigInt(Date.now() - 10000) * 1000000n;
+    await expectRejected(signedUpdate(OTHER_KEY, earlier, 61000000000n), "StaleUpdate");
+
+    await configure([PUBLISHER_KEY], FEED_PARAMS);
+  });
+
   it.skip("Delegate price feed", async () => {
     // Skipped: Requires test-mode feature for authorization
     // This test demonstrates ephemeral rollups SDK delegation functionality
@@ -234,12 +588,53 @@ describe("pricing-oracle", () => {
     console.log("Undelegate test skipped - requires test-mode authorization");
   });
 
-  it("Sample external price feed", async () => {
-    // Note: This test requires a real Pyth price update account to work properly
-    // For testing purposes, we'll skip it
-    console.log(
-      "Sample test - requires external Pyth price update account (skipped)"
-    );
+  const SAMPLE_PARAMS = {
+    feedId: FEED_ID,
+    maximumAge: new anchor.BN(60),
+    maxConfBps: 0,
+  };
+
+  function feedConfigPda(priceFeed: PublicKey): PublicKey {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("feed_config"), priceFeed.toBuffer()],
+      program.programId
+    )[0];
+  }
+
+  async function sample(params: any, priceUpdate = priceFeedPda) {
+    const { raw } = await program.methods
+      .sample(params)
+      .accounts({
+        payer: provider.wallet.publicKey,
+        priceUpdate,
+        feedConfig: feedConfigPda(priceUpdate),
+      })
+      .simulate();
+    const prefix = `Program return: ${program.programId} `;
+    const data = raw.find((log) => log.startsWith(prefix)).slice(prefix.length);
+    return program.coder.types.decode("SampledPrice", Buffer.from(data, "base64"));
+  }
+
+  it("Sample returns our feed's price at 18 decimals", async () => {
+    // The aggregate test left 605 ± 5 at exponent -8.
+    const sampled = await sample(SAMPLE_PARAMS);
+    assert.equal(sampled.price.toString(), "605000000000000000000");
+    assert.equal(sampled.conf.toString(), "5000000000000000000");
+    assert.equal(sampled.exponent, -18);
+  });
+
+  it("Sample enforces the caller's limits", async () => {
+    const rejects = async (params: any, code: string) => {
+      try {
+        await sample(params);
+        assert.fail(`sample succeeded, expected ${code}`);
+      } catch (error) {
+        assert.include(JSON.stringify(error.simulationResponse ?? error.toString()), code);
+      }
+    };
+    // 5 / 605 is about 83 bps.
+    await rejects({ ...SAMPLE_PARAMS, maxConfBps: 50 }, "ConfidenceTooWide");
+    await rejects({ ...SAMPLE_PARAMS, feedId: Array(32).fill(2) }, "MismatchedFeedId");
   });
 
   it("Initialize multiple price feeds", async () => {
@@ -271,10 +666,89 @@ describe("pricing-oracle", () => {
     console.log("Multiple price feeds initialized successfully");
   });
 
-  it.skip("Close price feed", async () => {
-    // Skipped: Requires test-mode feature for authorization
-    // Cleanup is now handled in the after() hook
-    console.log("Close test skipped - requires test-mode authorization");
+  it("Enumerates feeds with their metadata", async () => {
+    const feeds = await program.account.feedConfig.all();
+    const symbols = feeds
+      .filter((feed) => feed.account.provider === PROVIDER)
+      .map((feed) => feed.account.symbol)
+      .sort();
+    assert.includeMembers(symbols, ["BTC/USD", "ETH/USD"]);
+    const btc = feeds.find((feed) =>
+      feed.account.priceFeed.equals(priceFeedPda)
+    );
+    assert.deepEqual(btc.account.status, { active: {} });
+
+    const registry = await program.account.registry.fetch(registryPda);
+    assert.ok(registry.feedCount.toNumber() >= 2);
+  });
+
+  it("Retired feeds cannot be resumed", async () => {
+    const admin = { admin: provider.wallet.publicKey, price_feed: ethPriceFeedPda };
+    await program.methods.retireFeed(PROVIDER, "ETH/USD").accounts(admin).rpc();
+    try {
+      await program.methods.resumeFeed(PROVIDER, "ETH/USD").accounts(admin).rpc();
+      assert.fail("a retired feed was resumed");
+    } catch (error) {
+      assert.include(error.toString(), "FeedRetired");
+    }
+    try {
+      await sample({ ...SAMPLE_PARAMS, feedId: Array(32).fill(2) }, ethPriceFeedPda);
+      assert.fail("a retired feed was sampled");
+    } catch (error) {
+      assert.include(JSON.stringify(error.simulationResponse ?? error.toString()), "FeedRetired");
+    }
+  });
+
+  it("Feeds with a config cannot be attached again", async () => {
+    try {
+      await program.methods
+        .attachFeedConfig(PROVIDER, SYMBOL)
+        .accounts({ payer: provider.wallet.publicKey })
+        .rpc();
+      assert.fail("a feed config was attached twice");
+    } catch (error) {
+      assert.include(error.toString(), "already in use");
+    }
+  });
+
+  it("Rejects updates signed for another feed", async () => {
+    const now = BigInt(Date.now()) * 1000000n;
+    await expectRejected(
+      signedUpdate(PUBLISHER_KEY, now, 60500000000n, 0n, EXPONENT, ethPriceFeedPda),
+      "UnknownPublisher"
+    );
+  });
+
+  it("Only the admin closes feeds", async () => {
+    const stranger = anchor.web3.Keypair.generate();
+    await fundWithTransfer(stranger.publicKey, LAMPORTS_PER_SOL / 10);
+    try {
+      await program.methods
+        .closePriceFeed(PROVIDER, "ETH/USD")
+        .accounts({ payer: stranger.publicKey })
+        .signers([stranger])
+        .rpc();
+      assert.fail("a non-admin closed a feed");
+    } catch (error) {
+      assert.include(error.toString(), "Unauthorized");
+    }
+  });
+
+  it("Close price feed", async () => {
+    const feedCount = async () =>
+      (await program.account.registry.fetch(registryPda)).feedCount.toNumber();
+    const before = await feedCount();
+    await program.methods
+      .closePriceFeed(PROVIDER, "ETH/USD")
+      .accounts({ payer: provider.wallet.publicKey })
+      .rpc();
+    assert.equal(await feedCount(), before - 1);
+    const [feedConfigPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("feed_config"), ethPriceFeedPda.toBuffer()],
+      program.programId
+    );
+    assert.isNull(await program.account.priceUpdateV3.fetchNullable(ethPriceFeedPda));
+    assert.isNull(await program.account.feedConfig.fetchNullable(feedConfigPda));
   });
 
   it("Error handling: test mode allows any payer", async () => {
@@ -308,10 +782,11 @@ describe("pricing-oracle", () => {
     const task = taskKey(taskQueue, taskId)[0];
 
     const tx = await program.methods
-      .schedule(taskId)
+      .schedule(taskId, SAMPLE_PARAMS)
       .accountsPartial({
         payer: provider.publicKey,
         priceUpdate,
+        feedConfig: feedConfigPda(priceUpdate),
         systemProgram: SystemProgram.programId,
         taskQueue,
         taskQueueAuthority,
@@ -0,0 +1,97 @@
+//! `#[storable]`: one attribute instead of the six backend derives, plus a layout description.
+//!
+//! A derive macro cannot add other derives, so this is an attribute macro. The
+//! generated code names `::borsh`, `::serde`, `::wincode` and `::ch1`, so the
+//! using crate depends on all four.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{ToTokens, quote};
+use syn::{Data, DeriveInput, Error, parse_macro_input, parse_quote};
+
+/// Derives Borsh, serde and wincode (de)serialization for a struct and implements
+/// `ch1::schema::Storable` and `ch1::schema::BorshSize` for it.
+///
+/// Put it above any other `#[derive]` on the struct.
+#[proc_macro_attribute]
+pub fn storable(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if !attr.is_empty() {
+        return Error::new(proc_macro2::Span::call_site(), "#[storable] takes no arguments")
+            .to_compile_error()
+            .into();
+    }
+    let input = parse_macro_input!(item as DeriveInput);
+    expand(input).unwrap_or_else(Error::into_compile_error).into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(&input.ident, "#[storable] supports structs only"));
+    };
+    let ident = &input.ident;
+    let name = ident.to_string();
+    let field_names: Vec<String> = data
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(i, f)| f.ident.as_ref().map_or_else(|| i.to_string(), ToString::to_string))
+        .collect();
+    let tys: Vec<_> = data.fields.iter().map(|f| &f.ty).collect();
+    let ty_names: Vec<String> = tys.iter().map(|ty| type_name(ty)).collect();
+
+    let mut generics = input.generics.clone();
+    let where_clause = generics.make_where_clause();
+    for ty in &tys {
+        where_clause
+            .predicates
+            .push(parse_quote!(#ty: ::ch1::schema::BorshSize));
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        #[derive(
+            ::borsh::BorshSerialize,
+            ::borsh::BorshDeserialize,
+            ::serde::Serialize,
+            ::serde::Deserialize,
+            ::wincode::SchemaWrite,
+            ::wincode::SchemaRead,
+        )]
+        #input
+
+        impl #impl_generics ::ch1::schema::BorshSize for #ident #ty_generics #where_clause {
+            const FIXED_SIZE: ::core::option::Option<usize> = ::ch1::schema::sum(&[
+                #(<#tys as ::ch1::schema::BorshSize>::FIXED_SIZE),*
+            ]);
+        }
+
+        impl #impl_generics ::ch1::schema::Storable for #ident #ty_generics #where_clause {
+            fn schema() -> ::ch1::schema::Schema {
+                ::ch1::schema::Schema::new(#name, &[
+                    #((#field_names, #ty_names, <#tys as ::ch1::schema::BorshSize>::FIXED_SIZE)),*
+                ])
+            }
+        }
+    })
+}
+
+/// The type as written, without the spaces `to_string` puts between tokens:
+/// `Vec < u8 >` becomes `Vec<u8>`, `[u8 ; 32]` becomes `[u8; 32]`.
+fn type_name(ty: &syn::Type) -> String {
+    let raw = ty.to_token_stream().to_string();
+    let chars: Vec<char> = raw.chars().collect();
+    let word = |c: &char| c.is_alphanumeric() || *c == '_';
+    let mut out = String::with_capacity(raw.len());
+    for (i, c) in chars.iter().enumerate() {
+        if *c == ' ' {
+            let prev = out.chars().last();
+            let next = chars.get(i + 1);
+            let between_words = prev.as_ref().is_some_and(word) && next.is_some_and(word);
+            if !between_words && !matches!(prev, Some(';' | ',')) {
+                continue;
+            }
+        }
+        out.push(*c);
+    }
+    out
+}
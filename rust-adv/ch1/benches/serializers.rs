@@ -0,0 +1,230 @@
+//! Serializer benchmarks over a payload matrix.
+//!
+//! `cargo bench -p ch1` times `to_bytes`, `from_bytes`, `Storage::save` and
+//! `Storage::load` for every serializer on every payload, then writes a
+//! tab-separated report (`serializer payload op encoded_bytes mean_ns mib_per_s`)
+//! to `$BENCH_REPORT`, or `report.tsv` next to criterion's own output.
+
+use std::{
+    collections::BTreeMap,
+    env,
+    fmt::Debug,
+    fs,
+    hint::black_box,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use ch1::{Borsh, Json, Person, Serializer, Storage, Wincode};
+use criterion::{BenchmarkGroup, BenchmarkId, Criterion, Throughput, measurement::WallTime};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use wincode::{SchemaRead, SchemaWrite, config::DefaultConfig};
+
+const OPS: [&str; 4] = ["serialize", "deserialize", "save", "load"];
+
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, SchemaWrite, SchemaRead, Debug)]
+struct Account {
+    owner: [u8; 32],
+    lamports: u64,
+    executable: bool,
+    label: String,
+    data: Vec<u8>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, SchemaWrite, SchemaRead, Debug)]
+struct Snapshot {
+    slot: u64,
+    accounts: Vec<Account>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, SchemaWrite, SchemaRead, Debug)]
+struct Order {
+    id: u64,
+    maker: Party,
+    taker: Option<Party>,
+    legs: Vec<Leg>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, SchemaWrite, SchemaRead, Debug)]
+struct Party {
+    name: String,
+    wallet: [u8; 32],
+    fee_bps: u16,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, SchemaWrite, SchemaRead, Debug)]
+struct Leg {
+    mint: [u8; 32],
+    amount: u64,
+    limit_price: Option<i64>,
+}
+
+fn account(i: u64) -> Account {
+    Account {
+        owner: [i as u8; 32],
+        lamports: 1_000_000 + i,
+        executable: i.is_multiple_of(7),
+        label: format!("account-{i:04}"),
+        data: (0..1024).map(|b| (b as u64 ^ i) as u8).collect(),
+    }
+}
+
+fn party(name: &str) -> Party {
+    Party {
+        name: name.to_string(),
+        wallet: [9; 32],
+        fee_bps: 25,
+    }
+}
+
+/// One row of the report, before timings are read back from criterion.
+struct Row {
+    serializer: &'static str,
+    payload: &'static str,
+    encoded: usize,
+}
+
+fn bench_serializer<T: Debug, S: Serializer<T> + Clone>(
+    group: &mut BenchmarkGroup<'_, WallTime>,
+    payload: &'static str,
+    serializer: S,
+    value: &T,
+    rows: &mut Vec<Row>,
+) {
+    let name = serializer.name();
+    let bytes = serializer.to_bytes(value).unwrap();
+    group.throughput(Throughput::Bytes(bytes.len() as u64));
+
+    group.bench_function(BenchmarkId::new(name, "serialize"), |b| {
+        b.iter(|| serializer.to_bytes(black_box(value)).unwrap())
+    });
+    group.bench_function(BenchmarkId::new(name, "deserialize"), |b| {
+        b.iter(|| serializer.from_bytes(black_box(&bytes)).unwrap())
+    });
+
+    // Built once, outside the timed loop.
+    let mut storage = Storage::new(serializer.clone());
+    group.bench_function(BenchmarkId::new(name, "save"), |b| {
+        b.iter(|| storage.save(black_box(value)).unwrap())
+    });
+    group.bench_function(BenchmarkId::new(name, "load"), |b| b.iter(|| storage.load().unwrap()));
+
+    rows.push(Row {
+        serializer: name,
+        payload,
+        encoded: bytes.len(),
+    });
+}
+
+fn bench_payload<T>(c: &mut Criterion, payload: &'static str, value: &T, rows: &mut Vec<Row>)
+where
+    T: Debug
+        + BorshSerialize
+        + BorshDeserialize
+        + Serialize
+        + DeserializeOwned
+        + SchemaWrite<DefaultConfig, Src = T>
+        + for<'de> SchemaRead<'de, DefaultConfig, Dst = T>,
+{
+    let mut group = c.benchmark_group(payload);
+    bench_serializer(&mut group, payload, Borsh, value, rows);
+    bench_serializer(&mut group, payload, Wincode, value, rows);
+    bench_serializer(&mut group, payload, Json, value, rows);
+    #[cfg(feature = "bincode")]
+    bench_serializer(&mut group, payload, ch1::formats::Bincode, value, rows);
+    #[cfg(feature = "msgpack")]
+    bench_serializer(&mut group, payload, ch1::formats::MessagePack, value, rows);
+    #[cfg(feature = "cbor")]
+    bench_serializer(&mut group, payload, ch1::formats::Cbor, value, rows);
+    #[cfg(feature = "postcard")]
+    bench_serializer(&mut group, payload, ch1::formats::Postcard, value, rows);
+    group.finish();
+}
+
+fn output_dir() -> PathBuf {
+    if let Some(home) = env::var_os("CRITERION_HOME") {
+        return home.into();
+    }
+    let target = env::var_os("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("../target"));
+    target.join("criterion")
+}
+
+/// Mean time per iteration in ns, if criterion measured `id` during this run.
+fn mean_ns(out: &Path, id: &str, started: SystemTime) -> Option<f64> {
+    let path = out.join(id).join("new/estimates.json");
+    if fs::metadata(&path).ok()?.modified().ok()? < started {
+        return None;
+    }
+    let estimates: serde_json::Value = serde_json::from_slice(&fs::read(path).ok()?).ok()?;
+    estimates["mean"]["point_estimate"].as_f64()
+}
+
+fn write_report(out: &Path, started: SystemTime, rows: &[Row]) {
+    let mut report = String::from("serializer\tpayload\top\tencoded_bytes\tmean_ns\tmib_per_s\n");
+    let mut measured = 0;
+    for row in rows {
+        for op in OPS {
+            let id = format!("{}/{}/{}", row.payload, row.serializer, op);
+            let Some(ns) = mean_ns(out, &id, started) else {
+                continue;
+            };
+            let mib_per_s = row.encoded as f64 / (ns / 1e9) / (1 << 20) as f64;
+            report += &format!(
+                "{}\t{}\t{}\t{}\t{:.1}\t{:.1}\n",
+                row.serializer, row.payload, op, row.encoded, ns, mib_per_s
+            );
+            measured += 1;
+        }
+    }
+    if measured == 0 {
+        return;
+    }
+    let path = env::var_os("BENCH_REPORT")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| out.join("report.tsv"));
+    fs::write(&path, &report).expect("write benchmark report");
+    print!("\n{report}");
+    println!("report written to {}", path.display());
+}
+
+fn main() {
+    let started = SystemTime::now();
+    let out = output_dir();
+    let mut c = Criterion::default().output_directory(&out).configure_from_args();
+    let mut rows = Vec::new();
+
+    let small = Person {
+        color_hex: "ffffff".to_string(),
+        fav_num: 702496809348,
+    };
+    bench_payload(&mut c, "small", &small, &mut rows);
+    bench_payload(&mut c, "medium", &account(1), &mut rows);
+    let large = Snapshot {
+        slot: 312_000_000,
+        accounts: (0..512).map(account).collect(),
+    };
+    bench_payload(&mut c, "large", &large, &mut rows);
+    let nested = Order {
+        id: 42,
+        maker: party("maker"),
+        taker: Some(party("taker")),
+        legs: (0..16)
+            .map(|i| Leg {
+                mint: [i as u8; 32],
+                amount: i * 1_000,
+                limit_price: i.is_multiple_of(2).then_some(-(i as i64)),
+            })
+            .collect(),
+    };
+    bench_payload(&mut c, "nested", &nested, &mut rows);
+    let vec: Vec<u64> = (0..10_000).map(|i| i * 2_654_435_761).collect();
+    bench_payload(&mut c, "vec", &vec, &mut rows);
+    let map: BTreeMap<String, u64> = (0..1_000).map(|i| (format!("key-{i:04}"), i)).collect();
+    bench_payload(&mut c, "map", &map, &mut rows);
+
+    c.final_summary();
+    write_report(&out, started, &rows);
+}
@@ -0,0 +1,250 @@
+//! Anchor account data: an 8-byte discriminator followed by the Borsh encoded account.
+//!
+//! The discriminator is `sha256("account:<Name>")[..8]`, as written by `#[account]`.
+//! Mirrors of the accounts in this repo's programs are included so their raw
+//! account data can be read through `Storage` without depending on `anchor-lang`.
+
+use std::{fmt::Debug as db, io::Write};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use wincode::{SchemaRead, SchemaWrite};
+
+use crate::{
+    Serializer,
+    envelope::Format,
+    error::StorageError,
+    schema::{BorshSize, storable},
+};
+
+pub const DISCRIMINATOR_LEN: usize = 8;
+
+/// An account type declared with Anchor's `#[account]`.
+pub trait AnchorAccount {
+    /// The struct name in the program, which the discriminator is derived from.
+    const NAME: &'static str;
+
+    fn discriminator() -> [u8; DISCRIMINATOR_LEN] {
+        discriminator(Self::NAME)
+    }
+}
+
+pub fn discriminator(name: &str) -> [u8; DISCRIMINATOR_LEN] {
+    let hash = Sha256::digest(format!("account:{name}"));
+    hash[..DISCRIMINATOR_LEN].try_into().unwrap()
+}
+
+/// Anchor account serializer. Decoding ignores bytes after the account, since
+/// accounts are usually allocated larger than their Borsh size.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Anchor;
+
+impl<T: db + AnchorAccount + BorshSerialize + BorshDeserialize> Serializer<T> for Anchor {
+    fn to_bytes(&self, value: &T) -> Result<Vec<u8>, StorageError> {
+        let mut bytes = T::discriminator().to_vec();
+        value
+            .serialize(&mut bytes)
+            .map_err(|e| StorageError::encode("Anchor", e))?;
+        Ok(bytes)
+    }
+
+    fn from_bytes(&self, bytes: &[u8]) -> Result<T, StorageError> {
+        let Some((found, body)) = bytes.split_first_chunk::<DISCRIMINATOR_LEN>() else {
+            return Err(StorageError::Truncated {
+                expected: DISCRIMINATOR_LEN,
+                actual: bytes.len(),
+            });
+        };
+        let expected = T::discriminator();
+        if *found != expected {
+            return Err(StorageError::DiscriminatorMismatch {
+                expected,
+                found: *found,
+            });
+        }
+        let mut rest = body;
+        T::deserialize(&mut rest)
+            .map_err(|e| StorageError::decode("Anchor", Some(bytes.len() - rest.len()), e))
+    }
+
+    fn serialize_into(&self, value: &T, writer: &mut impl Write) -> Result<(), StorageError> {
+        writer.write_all(&T::discriminator())?;
+        value
+            .serialize(writer)
+            .map_err(|e| StorageError::encode("Anchor", e))
+    }
+
+    fn name(&self) -> &'static str {
+        "Anchor"
+    }
+
+    fn format(&self) -> Format {
+        Format::Anchor
+    }
+}
+
+/// `anchor-escrow`'s `Escrow`.
+#[storable]
+#[derive(Clone, PartialEq, Debug)]
+pub struct Escrow {
+    pub seed: u64,
+    pub maker: [u8; 32],
+    pub mint_a: [u8; 32],
+    pub mint_b: [u8; 32],
+    pub receive: u64,
+    pub bump: u8,
+}
+
+impl AnchorAccount for Escrow {
+    const NAME: &'static str = "Escrow";
+}
+
+/// `transfer-hook-vault`'s `Vault`.
+#[storable]
+#[derive(Clone, PartialEq, Debug)]
+pub struct Vault {
+    pub mint_token: [u8; 32],
+    pub admin: [u8; 32],
+    pub fees: u8,
+    pub bump: u8,
+}
+
+impl AnchorAccount for Vault {
+    const NAME: &'static str = "Vault";
+}
+
+/// Pyth's `VerificationLevel`.
+#[derive(
+    BorshSerialize, BorshDeserialize, Serialize, Deserialize, SchemaWrite, SchemaRead, Clone, Copy, PartialEq, Debug,
+)]
+pub enum VerificationLevel {
+    Partial { num_signatures: u8 },
+    Full,
+}
+
+impl BorshSize for VerificationLevel {
+    const FIXED_SIZE: Option<usize> = None;
+}
+
+/// Pyth's `PriceFeedMessage`.
+#[storable]
+#[derive(Clone, PartialEq, Debug)]
+pub struct PriceFeedMessage {
+    pub feed_id: [u8; 32],
+    pub price: i64,
+    pub conf: u64,
+    pub exponent: i32,
+    pub publish_time: i64,
+    pub prev_publish_time: i64,
+    pub ema_price: i64,
+    pub ema_conf: u64,
+}
+
+/// `pricing-oracle`'s `PriceUpdateV3`.
+#[storable]
+#[derive(Clone, PartialEq, Debug)]
+pub struct PriceUpdateV3 {
+    pub write_authority: [u8; 32],
+    pub verification_level: VerificationLevel,
+    pub price_message: PriceFeedMessage,
+    pub posted_slot: u64,
+}
+
+impl AnchorAccount for PriceUpdateV3 {
+    const NAME: &'static str = "PriceUpdateV3";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Borsh, Storage, schema::Storable};
+
+    fn escrow() -> Escrow {
+        Escrow {
+            seed: 42,
+            maker: [1; 32],
+            mint_a: [2; 32],
+            mint_b: [3; 32],
+            receive: 1_000_000,
+            bump: 254,
+        }
+    }
+
+    fn price_update() -> PriceUpdateV3 {
+        PriceUpdateV3 {
+            write_authority: [7; 32],
+            verification_level: VerificationLevel::Full,
+            price_message: PriceFeedMessage {
+                feed_id: [9; 32],
+                price: 6_512_345_000_000,
+                conf: 3_250_000_000,
+                exponent: -8,
+                publish_time: 1_760_000_000,
+                prev_publish_time: 1_759_999_999,
+                ema_price: 6_500_000_000_000,
+                ema_conf: 3_000_000_000,
+            },
+            posted_slot: 312_000_000,
+        }
+    }
+
+    #[test]
+    fn discriminators_match_anchor() {
+        assert_eq!(Escrow::discriminator(), [31, 213, 123, 187, 186, 22, 218, 155]);
+        assert_eq!(Vault::discriminator(), [211, 8, 232, 43, 2, 152, 117, 119]);
+        assert_eq!(PriceUpdateV3::discriminator(), [234, 161, 14, 36, 172, 239, 15, 232]);
+    }
+
+    #[test]
+    fn decodes_raw_account_data() {
+        // Account data as fetched from the cluster: discriminator, body, then the
+        // zeroed rest of the allocation.
+        let mut data = PriceUpdateV3::discriminator().to_vec();
+        data.extend(Borsh.to_bytes(&price_update()).unwrap());
+        data.resize(8 + 160, 0);
+        let decoded: PriceUpdateV3 = Anchor.from_bytes(&data).unwrap();
+        assert_eq!(decoded, price_update());
+
+        let bytes = Anchor.to_bytes(&escrow()).unwrap();
+        assert_eq!(bytes.len(), 8 + Escrow::schema().fixed_size.unwrap());
+        assert_eq!(Serializer::<Escrow>::from_bytes(&Anchor, &bytes).unwrap(), escrow());
+
+        let vault = Vault {
+            mint_token: [4; 32],
+            admin: [5; 32],
+            fees: 25,
+            bump: 253,
+        };
+        let mut st = Storage::new(Anchor);
+        st.save(&vault).unwrap();
+        assert_eq!(st.load().unwrap(), vault);
+    }
+
+    #[test]
+    fn rejects_other_accounts() {
+        let bytes = Anchor.to_bytes(&escrow()).unwrap();
+        let err = Serializer::<Vault>::from_bytes(&Anchor, &bytes).unwrap_err();
+        assert!(matches!(
+            err,
+            StorageError::DiscriminatorMismatch { expected, found }
+                if expected == Vault::discriminator() && found == Escrow::discriminator()
+        ));
+        assert!(matches!(
+            Serializer::<Vault>::from_bytes(&Anchor, &bytes[..5]),
+            Err(StorageError::Truncated { expected: 8, actual: 5 })
+        ));
+        assert!(matches!(
+            Serializer::<Escrow>::from_bytes(&Anchor, &bytes[..20]),
+            Err(StorageError::Decode { serializer: "Anchor", .. })
+        ));
+
+        let mut st = Storage::new(Borsh);
+        st.save(&escrow()).unwrap();
+        let blob = st.blob().unwrap().unwrap().into_owned();
+        assert!(matches!(
+            Storage::<Escrow, _>::open(Anchor, blob),
+            Err(StorageError::FormatMismatch { .. })
+        ));
+    }
+}
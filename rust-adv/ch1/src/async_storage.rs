@@ -0,0 +1,287 @@
+//! `AsyncStorage`: `save` / `load` futures for tokio services, behind the `tokio` feature.
+//!
+//! Encoding and decoding of large payloads run on tokio's blocking pool so big
+//! snapshots do not stall the runtime's worker threads. Blobs use the same envelope
+//! and migrations as `Storage`, so a file written here can be opened with
+//! `backend::MmapFile` and the other way round.
+
+use std::{fmt::Debug as db, io, marker::PhantomData, path::PathBuf, sync::Arc};
+
+use tokio::{io::AsyncWriteExt, sync::watch};
+
+use crate::{Serializer, backend::tmp_path, current_payload, envelope, error::StorageError, migration::Migrations};
+
+/// Payloads from this many bytes up are handled on the blocking pool unless changed
+/// with `with_blocking_threshold`.
+pub const DEFAULT_BLOCKING_THRESHOLD: usize = 64 << 10;
+
+type SizeHint<T> = Box<dyn Fn(&T) -> usize + Send + Sync>;
+
+pub trait AsyncBackend: Send + Sync {
+    fn write(&mut self, blob: Vec<u8>) -> impl Future<Output = io::Result<()>> + Send;
+    fn read(&self) -> impl Future<Output = io::Result<Option<Vec<u8>>>> + Send;
+}
+
+/// One blob in a file, replaced atomically through a `*.tmp` sibling like the sync file backends.
+#[derive(Debug, Clone)]
+pub struct AsyncFile {
+    path: PathBuf,
+}
+
+impl AsyncFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        AsyncFile { path: path.into() }
+    }
+}
+
+impl AsyncBackend for AsyncFile {
+    async fn write(&mut self, blob: Vec<u8>) -> io::Result<()> {
+        let tmp = tmp_path(&self.path);
+        let mut file = tokio::fs::File::create(&tmp).await?;
+        file.write_all(&blob).await?;
+        file.sync_all().await?;
+        drop(file);
+        tokio::fs::rename(&tmp, &self.path).await
+    }
+
+    async fn read(&self) -> io::Result<Option<Vec<u8>>> {
+        match tokio::fs::read(&self.path).await {
+            Ok(blob) if blob.is_empty() => Ok(None),
+            Ok(blob) => Ok(Some(blob)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Publishes every saved blob on a `tokio::sync::watch` channel; other tasks
+/// `subscribe` and see each new version as it is saved.
+#[derive(Debug)]
+pub struct Watch {
+    sender: watch::Sender<Option<Arc<[u8]>>>,
+}
+
+impl Default for Watch {
+    fn default() -> Self {
+        Watch {
+            sender: watch::Sender::new(None),
+        }
+    }
+}
+
+impl Watch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<Option<Arc<[u8]>>> {
+        self.sender.subscribe()
+    }
+}
+
+impl AsyncBackend for Watch {
+    async fn write(&mut self, blob: Vec<u8>) -> io::Result<()> {
+        self.sender.send_replace(Some(blob.into()));
+        Ok(())
+    }
+
+    async fn read(&self) -> io::Result<Option<Vec<u8>>> {
+        Ok(self.sender.borrow().as_deref().map(<[u8]>::to_vec))
+    }
+}
+
+/// Async counterpart of `Storage`.
+pub struct AsyncStorage<T, S, B> {
+    backend: B,
+    serializer: Arc<S>,
+    schema_version: u16,
+    migrations: Arc<Migrations<S>>,
+    blocking_threshold: usize,
+    size_hint: Option<SizeHint<T>>,
+    _type: PhantomData<fn() -> T>,
+}
+
+impl<T, S, B> AsyncStorage<T, S, B>
+where
+    T: db + Send + 'static,
+    S: Serializer<T> + Send + Sync + 'static,
+    B: AsyncBackend,
+{
+    pub fn new(serializer: S, backend: B) -> Self {
+        AsyncStorage {
+            backend,
+            serializer: Arc::new(serializer),
+            schema_version: 0,
+            migrations: Arc::new(Migrations::new()),
+            blocking_threshold: DEFAULT_BLOCKING_THRESHOLD,
+            size_hint: None,
+            _type: PhantomData,
+        }
+    }
+
+    pub fn with_schema_version(mut self, version: u16) -> Self {
+        self.schema_version = version;
+        Arc::get_mut(&mut self.serializer)
+            .expect("the serializer is only shared while a save or load runs")
+            .set_schema_version(version);
+        self
+    }
+
+    /// Upgrades applied on `load` to blobs stamped with an older schema version.
+    pub fn with_migrations(mut self, migrations: Migrations<S>) -> Self {
+        self.migrations = Arc::new(migrations);
+        self
+    }
+
+    /// Payload size, in bytes, from which encoding and decoding run on the blocking pool.
+    pub fn with_blocking_threshold(mut self, threshold: usize) -> Self {
+        self.blocking_threshold = threshold;
+        self
+    }
+
+    /// Estimates the encoded size of a value before `save` encodes it. Without one,
+    /// every `save` encodes on the blocking pool.
+    pub fn with_size_hint(mut self, hint: impl Fn(&T) -> usize + Send + Sync + 'static) -> Self {
+        self.size_hint = Some(Box::new(hint));
+        self
+    }
+
+    pub async fn save(&mut self, value: T) -> Result<(), StorageError> {
+        let large = self.size_hint.as_ref().is_none_or(|hint| hint(&value) >= self.blocking_threshold);
+        let serializer = self.serializer.clone();
+        let schema_version = self.schema_version;
+        let encode = move || {
+            let bytes = serializer.to_bytes(&value)?;
+            let blob = envelope::seal(serializer.format(), schema_version, &bytes)?;
+            Ok::<_, StorageError>(blob)
+        };
+        let blob = if large {
+            on_blocking_pool(encode).await??
+        } else {
+            encode()?
+        };
+        self.backend.write(blob).await?;
+        Ok(())
+    }
+
+    pub async fn load(&self) -> Result<T, StorageError> {
+        let blob = self.backend.read().await?.ok_or(StorageError::Empty)?;
+        let large = blob.len() >= self.blocking_threshold;
+        let serializer = self.serializer.clone();
+        let schema_version = self.schema_version;
+        let migrations = self.migrations.clone();
+        let decode = move || {
+            let payload = current_payload(&blob, &*serializer, serializer.format(), schema_version, &migrations)?;
+            serializer.from_bytes(&payload)
+        };
+        if large {
+            on_blocking_pool(decode).await?
+        } else {
+            decode()
+        }
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+}
+
+/// Runs `f` on the blocking pool, re-raising its panic if it panicked.
+async fn on_blocking_pool<R: Send + 'static>(
+    f: impl FnOnce() -> R + Send + 'static,
+) -> Result<R, StorageError> {
+    match tokio::task::spawn_blocking(f).await {
+        Ok(result) => Ok(result),
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        Err(e) => Err(StorageError::Io(io::Error::other(e))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Borsh, Json, Person, Storage, Wincode, backend::MmapFile, load_any};
+
+    fn person(fav_num: u64) -> Person {
+        Person {
+            color_hex: "ffffff/000000".to_string(),
+            fav_num,
+        }
+    }
+
+    #[tokio::test]
+    async fn file_round_trip_shares_the_sync_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("person.bin");
+
+        let mut st = AsyncStorage::new(Borsh, AsyncFile::new(&path));
+        assert!(matches!(st.load().await, Err(StorageError::Empty)));
+        st.save(person(1)).await.unwrap();
+        st.save(person(2)).await.unwrap();
+        assert_eq!(st.load().await.unwrap(), person(2));
+        assert!(!dir.path().join("person.bin.tmp").exists());
+
+        let sync = Storage::<Person, _, _>::with_backend(Borsh, MmapFile::open(&path).unwrap());
+        assert_eq!(sync.load().unwrap(), person(2));
+
+        let json = AsyncStorage::<Person, _, _>::new(Json, AsyncFile::new(&path));
+        assert!(matches!(
+            json.load().await,
+            Err(StorageError::FormatMismatch { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn watch_subscribers_see_each_save() {
+        let backend = Watch::new();
+        let mut rx = backend.subscribe();
+        let mut st = AsyncStorage::new(Wincode, backend);
+
+        let reader = tokio::spawn(async move {
+            rx.changed().await.unwrap();
+            let blob = rx.borrow_and_update().clone().unwrap();
+            load_any::<Person>(&blob).unwrap().1
+        });
+        st.save(person(7)).await.unwrap();
+        assert_eq!(reader.await.unwrap(), person(7));
+        assert_eq!(st.load().await.unwrap(), person(7));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn large_payloads_use_the_blocking_pool() {
+        let big = || (0..2_000).map(person).collect::<Vec<_>>();
+        let mut st = AsyncStorage::new(Json, Watch::new()).with_blocking_threshold(1 << 10);
+        st.save(big()).await.unwrap();
+        assert_eq!(st.load().await.unwrap(), big());
+
+        // Values the hint calls small are encoded inline, large ones still go to the pool.
+        let mut st = st.with_size_hint(|people: &Vec<Person>| people.len() * 32);
+        st.save(vec![person(1)]).await.unwrap();
+        assert_eq!(st.load().await.unwrap(), vec![person(1)]);
+        st.save(big()).await.unwrap();
+        assert_eq!(st.load().await.unwrap(), big());
+    }
+
+    #[tokio::test]
+    async fn schema_versions_are_checked() {
+        let backend = Watch::new();
+        let mut rx = backend.subscribe();
+        let mut st = AsyncStorage::new(Borsh, backend).with_schema_version(1);
+        st.save(person(1)).await.unwrap();
+        let blob = rx.borrow_and_update().clone().unwrap().to_vec();
+
+        let mut newer = Watch::new();
+        newer.write(blob).await.unwrap();
+        let st = AsyncStorage::<Person, _, _>::new(Borsh, newer).with_schema_version(2);
+        assert!(matches!(
+            st.load().await,
+            Err(StorageError::MissingMigration { from: 1 })
+        ));
+
+        let st = st.with_migrations(Migrations::new().register(1, |old: Person| Person {
+            fav_num: old.fav_num + 1,
+            ..old
+        }));
+        assert_eq!(st.load().await.unwrap(), person(2));
+    }
+}
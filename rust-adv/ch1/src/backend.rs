@@ -0,0 +1,353 @@
+//! Where a `Storage` keeps its enveloped bytes.
+//!
+//! `AppendFile` only appends to its log, after cutting off a frame torn by a crash.
+//! `MmapFile` never modifies its target in place: every save writes a sibling `*.tmp`
+//! file, syncs it and renames it over the target.
+
+use std::{
+    borrow::Cow,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+pub trait Backend {
+    fn write(&mut self, blob: &[u8]) -> io::Result<()>;
+    fn read(&self) -> io::Result<Option<Cow<'_, [u8]>>>;
+    fn has_data(&self) -> bool;
+}
+
+/// Keeps the latest blob in memory. This is the default backend.
+#[derive(Debug, Default, Clone)]
+pub struct Memory {
+    data: Option<Vec<u8>>,
+}
+
+impl From<Vec<u8>> for Memory {
+    fn from(blob: Vec<u8>) -> Self {
+        Memory { data: Some(blob) }
+    }
+}
+
+impl Backend for Memory {
+    fn write(&mut self, blob: &[u8]) -> io::Result<()> {
+        self.data = Some(blob.to_vec());
+        Ok(())
+    }
+
+    fn read(&self) -> io::Result<Option<Cow<'_, [u8]>>> {
+        Ok(self.data.as_deref().map(Cow::Borrowed))
+    }
+
+    fn has_data(&self) -> bool {
+        self.data.is_some()
+    }
+}
+
+/// Length and checksum in front of each blob in an `AppendFile` log.
+const FRAME_HEAD: usize = 8;
+/// Length repeated after each blob, so the newest frame can be found from the end.
+const FRAME_TAIL: usize = 4;
+
+/// Log of every saved blob, each framed as `len u32 | crc32 u32 | blob | len u32`
+/// (little endian). Saves append one frame; `read` seeks to the newest one. A save cut
+/// short by a crash leaves a torn frame at the end: reads skip it and fall back to the
+/// newest intact frame, and the next save truncates it before appending.
+#[derive(Debug, Clone)]
+pub struct AppendFile {
+    path: PathBuf,
+}
+
+impl AppendFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        AppendFile { path: path.into() }
+    }
+
+    fn open_log(&self) -> io::Result<Option<File>> {
+        match File::open(&self.path) {
+            Ok(file) => Ok(Some(file)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Every intact blob in the log, oldest first. A torn or corrupt frame ends the log.
+    pub fn records(&self) -> io::Result<Vec<Vec<u8>>> {
+        let Some(mut file) = self.open_log()? else {
+            return Ok(Vec::new());
+        };
+        let log = read_log(&mut file)?;
+        Ok(split_frames(&log).0.into_iter().map(<[u8]>::to_vec).collect())
+    }
+}
+
+fn read_log(file: &mut File) -> io::Result<Vec<u8>> {
+    let mut log = Vec::new();
+    file.seek(SeekFrom::Start(0))?;
+    file.read_to_end(&mut log)?;
+    Ok(log)
+}
+
+/// The blob of the frame that ends a log of `size` bytes, or `None` if that frame is torn.
+fn newest_frame(file: &mut File, size: u64) -> io::Result<Option<Vec<u8>>> {
+    if size < (FRAME_HEAD + FRAME_TAIL) as u64 {
+        return Ok(None);
+    }
+    let mut tail = [0u8; FRAME_TAIL];
+    file.seek(SeekFrom::End(-(FRAME_TAIL as i64)))?;
+    file.read_exact(&mut tail)?;
+    let frame_len = (FRAME_HEAD + u32::from_le_bytes(tail) as usize + FRAME_TAIL) as u64;
+    if frame_len > size {
+        return Ok(None);
+    }
+    let mut frame = vec![0u8; frame_len as usize];
+    file.seek(SeekFrom::Start(size - frame_len))?;
+    file.read_exact(&mut frame)?;
+    Ok(check_frame(&frame).map(<[u8]>::to_vec))
+}
+
+/// Splits `log` into the blobs of its leading intact frames and the number of bytes they
+/// span. Scanning stops at the first torn or corrupt frame.
+fn split_frames(log: &[u8]) -> (Vec<&[u8]>, usize) {
+    let mut blobs = Vec::new();
+    let mut rest = log;
+    while rest.len() >= FRAME_HEAD + FRAME_TAIL {
+        let len = u32::from_le_bytes(rest[..4].try_into().unwrap()) as usize;
+        let Some(blob) = rest.get(..FRAME_HEAD + len + FRAME_TAIL).and_then(check_frame) else {
+            break;
+        };
+        blobs.push(blob);
+        rest = &rest[FRAME_HEAD + len + FRAME_TAIL..];
+    }
+    (blobs, log.len() - rest.len())
+}
+
+/// The blob of a whole frame, or `None` if its lengths or checksum do not match.
+fn check_frame(frame: &[u8]) -> Option<&[u8]> {
+    let len = frame.len().checked_sub(FRAME_HEAD + FRAME_TAIL)?;
+    let head_len = u32::from_le_bytes(frame[..4].try_into().unwrap()) as usize;
+    let checksum = u32::from_le_bytes(frame[4..8].try_into().unwrap());
+    let tail_len = u32::from_le_bytes(frame[frame.len() - FRAME_TAIL..].try_into().unwrap()) as usize;
+    let blob = &frame[FRAME_HEAD..FRAME_HEAD + len];
+    (head_len == len && tail_len == len && crc32fast::hash(blob) == checksum).then_some(blob)
+}
+
+impl Backend for AppendFile {
+    fn write(&mut self, blob: &[u8]) -> io::Result<()> {
+        let len = u32::try_from(blob.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "blob larger than 4 GiB"))?;
+        let mut frame = Vec::with_capacity(FRAME_HEAD + blob.len() + FRAME_TAIL);
+        frame.extend_from_slice(&len.to_le_bytes());
+        frame.extend_from_slice(&crc32fast::hash(blob).to_le_bytes());
+        frame.extend_from_slice(blob);
+        frame.extend_from_slice(&len.to_le_bytes());
+
+        let created = !self.path.exists();
+        let mut file = OpenOptions::new().create(true).read(true).append(true).open(&self.path)?;
+        let size = file.metadata()?.len();
+        if size > 0 && newest_frame(&mut file, size)?.is_none() {
+            let log = read_log(&mut file)?;
+            file.set_len(split_frames(&log).1 as u64)?;
+        }
+        file.write_all(&frame)?;
+        file.sync_data()?;
+        if created {
+            sync_parent(&self.path)?;
+        }
+        Ok(())
+    }
+
+    fn read(&self) -> io::Result<Option<Cow<'_, [u8]>>> {
+        let Some(mut file) = self.open_log()? else {
+            return Ok(None);
+        };
+        let size = file.metadata()?.len();
+        if let Some(blob) = newest_frame(&mut file, size)? {
+            return Ok(Some(Cow::Owned(blob)));
+        }
+        let log = read_log(&mut file)?;
+        Ok(split_frames(&log).0.last().map(|blob| Cow::Owned(blob.to_vec())))
+    }
+
+    fn has_data(&self) -> bool {
+        self.read().is_ok_and(|blob| blob.is_some())
+    }
+}
+
+/// Holds one blob in a file and reads it back through a memory map.
+#[derive(Debug)]
+pub struct MmapFile {
+    path: PathBuf,
+    map: Option<memmap2::Mmap>,
+}
+
+impl MmapFile {
+    /// Maps `path` if it already exists; a missing file means an empty backend.
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let mut backend = MmapFile {
+            path: path.into(),
+            map: None,
+        };
+        backend.remap()?;
+        Ok(backend)
+    }
+
+    fn remap(&mut self) -> io::Result<()> {
+        self.map = None;
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        if file.metadata()?.len() == 0 {
+            return Ok(());
+        }
+        // SAFETY: saves never write into the mapped file; they rename a new file over
+        // the path, so the inode behind this map stays unchanged while it is alive.
+        self.map = Some(unsafe { memmap2::Mmap::map(&file)? });
+        Ok(())
+    }
+}
+
+impl Backend for MmapFile {
+    fn write(&mut self, blob: &[u8]) -> io::Result<()> {
+        replace_atomically(&self.path, blob)?;
+        self.remap()
+    }
+
+    fn read(&self) -> io::Result<Option<Cow<'_, [u8]>>> {
+        Ok(self.map.as_deref().map(Cow::Borrowed))
+    }
+
+    fn has_data(&self) -> bool {
+        self.map.is_some()
+    }
+}
+
+/// Sibling of `path` that saves are written to before being renamed over it.
+pub(crate) fn tmp_path(path: &Path) -> PathBuf {
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    path.with_file_name(tmp_name)
+}
+
+fn replace_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let tmp = tmp_path(path);
+    {
+        let mut file = File::create(&tmp)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp, path)
+}
+
+/// Makes the directory entry of a newly created `path` durable.
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> io::Result<()> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    File::open(parent)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Borsh, Json, Person, Storage};
+
+    fn person(fav_num: u64) -> Person {
+        Person {
+            color_hex: "ffffff/000000".to_string(),
+            fav_num,
+        }
+    }
+
+    #[test]
+    fn append_file_keeps_every_save_and_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("people.log");
+
+        let mut st = Storage::with_backend(Borsh, AppendFile::new(&path));
+        assert!(!st.has_data());
+        st.save(&person(1)).unwrap();
+        st.save(&person(2)).unwrap();
+        assert!(!dir.path().join("people.log.tmp").exists());
+
+        let reopened = Storage::<Person, _, _>::with_backend(Borsh, AppendFile::new(&path));
+        assert!(reopened.has_data());
+        assert_eq!(reopened.load().unwrap(), person(2));
+        assert_eq!(AppendFile::new(&path).records().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn append_file_survives_a_half_written_frame() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("people.log");
+        let mut st = Storage::with_backend(Borsh, AppendFile::new(&path));
+        st.save(&person(1)).unwrap();
+        let intact = fs::read(&path).unwrap();
+        st.save(&person(2)).unwrap();
+
+        // A crash halfway through the second save.
+        let mut torn = fs::read(&path).unwrap();
+        torn.truncate(intact.len() + (torn.len() - intact.len()) / 2);
+        fs::write(&path, &torn).unwrap();
+
+        let mut st = Storage::<Person, _, _>::with_backend(Borsh, AppendFile::new(&path));
+        assert!(st.has_data());
+        assert_eq!(st.load().unwrap(), person(1));
+        assert_eq!(AppendFile::new(&path).records().unwrap().len(), 1);
+
+        // The next save cuts the torn frame off before appending.
+        st.save(&person(3)).unwrap();
+        assert_eq!(st.load().unwrap(), person(3));
+        assert_eq!(AppendFile::new(&path).records().unwrap().len(), 2);
+
+        // A log holding only a torn frame is empty.
+        fs::write(&path, [10, 0, 0, 0, 1, 2]).unwrap();
+        assert!(!AppendFile::new(&path).has_data());
+        assert!(AppendFile::new(&path).read().unwrap().is_none());
+    }
+
+    #[test]
+    fn append_file_only_appends_and_checks_frames() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("people.log");
+        let mut log = AppendFile::new(&path);
+        log.write(b"first").unwrap();
+        let before = fs::read(&path).unwrap();
+        log.write(b"second").unwrap();
+        let after = fs::read(&path).unwrap();
+        assert_eq!(&after[..before.len()], before.as_slice());
+        assert_eq!(log.read().unwrap().unwrap().as_ref(), b"second");
+        assert_eq!(log.records().unwrap(), [b"first".to_vec(), b"second".to_vec()]);
+
+        // A flipped bit in the newest blob fails its checksum, so that frame is skipped.
+        let mut corrupt = after;
+        corrupt[before.len() + FRAME_HEAD] ^= 1;
+        fs::write(&path, &corrupt).unwrap();
+        assert_eq!(log.read().unwrap().unwrap().as_ref(), b"first");
+        assert_eq!(log.records().unwrap(), [b"first".to_vec()]);
+    }
+
+    #[test]
+    fn mmap_file_round_trip_and_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("person.bin");
+
+        let mut st = Storage::with_backend(Json, MmapFile::open(&path).unwrap());
+        assert!(!st.has_data());
+        st.save(&person(1)).unwrap();
+        st.save(&person(2)).unwrap();
+        assert_eq!(st.load().unwrap(), person(2));
+
+        let reopened = Storage::<Person, _, _>::with_backend(Json, MmapFile::open(&path).unwrap());
+        assert_eq!(reopened.load().unwrap(), person(2));
+    }
+}
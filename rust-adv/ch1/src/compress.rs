@@ -0,0 +1,349 @@
+//! `Compressed<S, C>` wraps any `Serializer` and compresses its output with a [`Codec`].
+//!
+//! Payload layout: `inner format id u8 | tag u8 | data`. The tag is `0` when the data
+//! is stored as is, or the codec id when compressed. Payloads below the threshold, or
+//! that do not shrink, are stored as is. The envelope records `Format::Compressed`, so
+//! `load_any` and a bare inner serializer refuse these blobs; open them with the same
+//! `Compressed` serializer.
+
+use std::fmt::Debug as db;
+
+use crate::{
+    Serializer,
+    envelope::{self, Format},
+    error::StorageError,
+};
+
+/// Payloads shorter than this are stored uncompressed unless changed with `with_threshold`.
+pub const DEFAULT_THRESHOLD: usize = 256;
+/// Decompressed payloads longer than this are rejected unless raised with `with_max_len`.
+pub const DEFAULT_MAX_LEN: usize = 64 << 20;
+
+const STORED: u8 = 0;
+
+pub trait Codec {
+    fn name(&self) -> &'static str;
+    /// Tag byte written in front of compressed payloads. Must not be `0`.
+    fn id(&self) -> u8;
+    fn compress(&self, bytes: &[u8]) -> Result<Vec<u8>, StorageError>;
+    /// Fails with `TooLarge` rather than inflating past `max_len` bytes.
+    fn decompress(&self, bytes: &[u8], max_len: usize) -> Result<Vec<u8>, StorageError>;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Compressed<S, C> {
+    inner: S,
+    codec: C,
+    threshold: usize,
+    max_len: usize,
+}
+
+impl<S, C: Codec> Compressed<S, C> {
+    pub fn new(inner: S, codec: C) -> Self {
+        Compressed {
+            inner,
+            codec,
+            threshold: DEFAULT_THRESHOLD,
+            max_len: DEFAULT_MAX_LEN,
+        }
+    }
+
+    /// Encoded size, in bytes, from which compression is attempted.
+    pub fn with_threshold(mut self, threshold: usize) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    pub fn with_max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+}
+
+impl<T: db, S: Serializer<T>, C: Codec> Serializer<T> for Compressed<S, C> {
+    fn to_bytes(&self, value: &T) -> Result<Vec<u8>, StorageError> {
+        let raw = self.inner.to_bytes(value)?;
+        if raw.len() >= self.threshold {
+            let packed = self.codec.compress(&raw)?;
+            if packed.len() < raw.len() {
+                let mut out = Vec::with_capacity(packed.len() + 2);
+                out.extend_from_slice(&[self.inner.format().id(), self.codec.id()]);
+                out.extend_from_slice(&packed);
+                return Ok(out);
+            }
+        }
+        let mut out = Vec::with_capacity(raw.len() + 2);
+        out.extend_from_slice(&[self.inner.format().id(), STORED]);
+        out.extend_from_slice(&raw);
+        Ok(out)
+    }
+
+    fn from_bytes(&self, bytes: &[u8]) -> Result<T, StorageError> {
+        let [format, tag, data @ ..] = bytes else {
+            return Err(StorageError::Truncated {
+                expected: 2,
+                actual: bytes.len(),
+            });
+        };
+        envelope::expect_format_id(self.inner.format(), *format)?;
+        match *tag {
+            STORED => self.inner.from_bytes(data),
+            id if id == self.codec.id() => self.inner.from_bytes(&self.codec.decompress(data, self.max_len)?),
+            id => Err(StorageError::decode(
+                self.codec.name(),
+                Some(1),
+                format!("unknown compression tag {id}"),
+            )),
+        }
+    }
+
+    fn set_schema_version(&mut self, version: u16) {
+        self.inner.set_schema_version(version);
+    }
+
+    fn name(&self) -> &'static str {
+        "Compressed"
+    }
+
+    fn format(&self) -> Format {
+        Format::Compressed
+    }
+}
+
+/// Reads at most `max_len + 1` bytes so oversized payloads are caught without inflating them fully.
+#[cfg(any(feature = "zstd", feature = "deflate"))]
+fn read_limited(name: &'static str, reader: impl std::io::Read, max_len: usize) -> Result<Vec<u8>, StorageError> {
+    use std::io::Read;
+
+    let mut out = Vec::new();
+    reader
+        .take(max_len as u64 + 1)
+        .read_to_end(&mut out)
+        .map_err(|e| StorageError::decode(name, None, e))?;
+    if out.len() > max_len {
+        return Err(StorageError::TooLarge {
+            len: out.len(),
+            max: max_len,
+        });
+    }
+    Ok(out)
+}
+
+#[cfg(feature = "zstd")]
+#[derive(Debug, Clone, Copy)]
+pub struct Zstd {
+    pub level: i32,
+}
+
+#[cfg(feature = "zstd")]
+impl Default for Zstd {
+    fn default() -> Self {
+        Zstd {
+            level: zstd::DEFAULT_COMPRESSION_LEVEL,
+        }
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl Codec for Zstd {
+    fn name(&self) -> &'static str {
+        "Zstd"
+    }
+
+    fn id(&self) -> u8 {
+        1
+    }
+
+    fn compress(&self, bytes: &[u8]) -> Result<Vec<u8>, StorageError> {
+        zstd::bulk::compress(bytes, self.level).map_err(|e| StorageError::encode("Zstd", e))
+    }
+
+    fn decompress(&self, bytes: &[u8], max_len: usize) -> Result<Vec<u8>, StorageError> {
+        let decoder = zstd::Decoder::new(bytes).map_err(|e| StorageError::decode("Zstd", None, e))?;
+        read_limited("Zstd", decoder, max_len)
+    }
+}
+
+/// LZ4 block format with the uncompressed size prepended. Fast, lower ratio.
+#[cfg(feature = "lz4")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Lz4;
+
+#[cfg(feature = "lz4")]
+impl Codec for Lz4 {
+    fn name(&self) -> &'static str {
+        "Lz4"
+    }
+
+    fn id(&self) -> u8 {
+        2
+    }
+
+    fn compress(&self, bytes: &[u8]) -> Result<Vec<u8>, StorageError> {
+        Ok(lz4_flex::compress_prepend_size(bytes))
+    }
+
+    fn decompress(&self, bytes: &[u8], max_len: usize) -> Result<Vec<u8>, StorageError> {
+        let (len, _) = lz4_flex::block::uncompressed_size(bytes)
+            .map_err(|e| StorageError::decode("Lz4", Some(0), e))?;
+        if len > max_len {
+            return Err(StorageError::TooLarge { len, max: max_len });
+        }
+        lz4_flex::decompress_size_prepended(bytes).map_err(|e| StorageError::decode("Lz4", None, e))
+    }
+}
+
+#[cfg(feature = "deflate")]
+#[derive(Debug, Clone, Copy)]
+pub struct Deflate {
+    /// 0 (none) to 9 (best).
+    pub level: u32,
+}
+
+#[cfg(feature = "deflate")]
+impl Default for Deflate {
+    fn default() -> Self {
+        Deflate { level: 6 }
+    }
+}
+
+#[cfg(feature = "deflate")]
+impl Codec for Deflate {
+    fn name(&self) -> &'static str {
+        "Deflate"
+    }
+
+    fn id(&self) -> u8 {
+        3
+    }
+
+    fn compress(&self, bytes: &[u8]) -> Result<Vec<u8>, StorageError> {
+        use std::io::Write;
+
+        let mut encoder =
+            flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::new(self.level));
+        encoder
+            .write_all(bytes)
+            .and_then(|_| encoder.finish())
+            .map_err(|e| StorageError::encode("Deflate", e))
+    }
+
+    fn decompress(&self, bytes: &[u8], max_len: usize) -> Result<Vec<u8>, StorageError> {
+        read_limited("Deflate", flate2::read::DeflateDecoder::new(bytes), max_len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Json, Person, Storage};
+
+    fn snapshot() -> Vec<Person> {
+        (0..200)
+            .map(|i| Person {
+                color_hex: format!("{:06x}", i % 4),
+                fav_num: i,
+            })
+            .collect()
+    }
+
+    fn round_trip<C: Codec + Copy>(codec: C) {
+        let compressed = Compressed::new(Json, codec);
+        let raw = Json.to_bytes(&snapshot()).unwrap();
+        let packed = compressed.to_bytes(&snapshot()).unwrap();
+        assert_eq!(packed[..2], [Format::Json.id(), codec.id()]);
+        assert!(packed.len() < raw.len() / 2, "{} -> {}", raw.len(), packed.len());
+
+        let mut st = Storage::new(compressed);
+        st.save(&snapshot()).unwrap();
+        assert_eq!(st.load().unwrap(), snapshot());
+
+        let mut corrupt = packed;
+        corrupt.truncate(corrupt.len() / 2);
+        assert!(Serializer::<Vec<Person>>::from_bytes(&compressed, &corrupt).is_err());
+    }
+
+    fn small_payloads_are_stored<C: Codec + Copy>(codec: C) {
+        let person = Person {
+            color_hex: "ffffff".to_string(),
+            fav_num: 6,
+        };
+        let compressed = Compressed::new(Json, codec);
+        let bytes = compressed.to_bytes(&person).unwrap();
+        assert_eq!(bytes[1], STORED);
+        assert_eq!(&bytes[2..], Json.to_bytes(&person).unwrap());
+        assert_eq!(Serializer::<Person>::from_bytes(&compressed, &bytes).unwrap(), person);
+
+        let eager = compressed.with_threshold(0);
+        assert_eq!(eager.to_bytes(&snapshot()).unwrap()[1], codec.id());
+    }
+
+    fn rejects_bombs_and_unknown_tags<C: Codec + Copy>(codec: C) {
+        let compressed = Compressed::new(Json, codec);
+        let bytes = compressed.to_bytes(&snapshot()).unwrap();
+        assert!(matches!(
+            compressed.with_max_len(64).from_bytes(&bytes),
+            Err::<Vec<Person>, _>(StorageError::TooLarge { max: 64, .. })
+        ));
+
+        let mut unknown = bytes;
+        unknown[1] = 0x7f;
+        assert!(matches!(
+            compressed.from_bytes(&unknown),
+            Err::<Vec<Person>, _>(StorageError::Decode { offset: Some(1), .. })
+        ));
+    }
+
+    fn blobs_name_the_wrapper<C: Codec + Copy>(codec: C) {
+        let mut st = Storage::new(Compressed::new(Json, codec));
+        st.save(&snapshot()).unwrap();
+        let blob = st.blob().unwrap().unwrap().into_owned();
+        assert!(matches!(
+            Storage::<Vec<Person>, _>::open(Json, blob.clone()),
+            Err(StorageError::FormatMismatch {
+                expected: Format::Json,
+                found: Format::Compressed
+            })
+        ));
+        assert!(matches!(
+            crate::load_any::<Vec<Person>>(&blob),
+            Err(StorageError::Unsupported(_))
+        ));
+
+        let payload = envelope::open(&blob).unwrap().1;
+        assert!(matches!(
+            Compressed::new(crate::Borsh, codec).from_bytes(payload),
+            Err::<Vec<Person>, _>(StorageError::FormatMismatch {
+                expected: Format::Borsh,
+                found: Format::Json
+            })
+        ));
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd() {
+        round_trip(Zstd::default());
+        small_payloads_are_stored(Zstd::default());
+        rejects_bombs_and_unknown_tags(Zstd::default());
+        blobs_name_the_wrapper(Zstd::default());
+    }
+
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn lz4() {
+        round_trip(Lz4);
+        small_payloads_are_stored(Lz4);
+        rejects_bombs_and_unknown_tags(Lz4);
+        blobs_name_the_wrapper(Lz4);
+    }
+
+    #[cfg(feature = "deflate")]
+    #[test]
+    fn deflate() {
+        round_trip(Deflate::default());
+        small_payloads_are_stored(Deflate::default());
+        rejects_bombs_and_unknown_tags(Deflate::default());
+        blobs_name_the_wrapper(Deflate::default());
+    }
+}
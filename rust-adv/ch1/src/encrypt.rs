@@ -0,0 +1,245 @@
+//! `Encrypted<S, A>` seals the output of any `Serializer` with an AEAD cipher.
+//!
+//! Payload layout: `inner format id u8 | schema version u16 | nonce | ciphertext | tag`.
+//! A fresh random nonce is drawn for every `to_bytes`. The schema version is the one
+//! `Storage` passes in through `Serializer::set_schema_version`. The format id and
+//! schema version are authenticated as associated data, so a payload cannot be replayed
+//! under a different serializer or passed off as another schema version, and `Storage`
+//! rejects blobs whose envelope header disagrees with the sealed version. The envelope
+//! records `Format::Encrypted`, so reading the blob without this wrapper fails with
+//! `StorageError::Encrypted`.
+
+use std::fmt::{self, Debug as db};
+
+use aead::{Aead, AeadCore, Generate, Key, KeyInit, Nonce, Payload, array::typenum::Unsigned};
+
+pub use chacha20poly1305::ChaCha20Poly1305;
+
+use crate::{
+    Serializer,
+    envelope::{self, Format},
+    error::StorageError,
+};
+
+/// Format id and schema version in front of the nonce.
+const PREFIX_LEN: usize = 3;
+
+/// Random 96-bit nonces are safe for about 2^32 saves per key; use
+/// `XChaCha20Poly1305` when a single key seals more than that.
+#[derive(Clone)]
+pub struct Encrypted<S, A = ChaCha20Poly1305> {
+    inner: S,
+    cipher: A,
+    schema_version: u16,
+}
+
+impl<S, A: KeyInit> Encrypted<S, A> {
+    pub fn new(inner: S, key: &Key<A>) -> Self {
+        Encrypted {
+            inner,
+            cipher: A::new(key),
+            schema_version: 0,
+        }
+    }
+}
+
+/// Schema version sealed into an `Encrypted` payload, if it is long enough to hold one.
+pub(crate) fn sealed_schema_version(payload: &[u8]) -> Option<u16> {
+    payload.get(1..PREFIX_LEN).map(|version| u16::from_le_bytes([version[0], version[1]]))
+}
+
+/// Leaves the cipher, and so the key schedule, out of debug output.
+impl<S: db, A> fmt::Debug for Encrypted<S, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Encrypted")
+            .field("inner", &self.inner)
+            .field("schema_version", &self.schema_version)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: db, S: Serializer<T>, A: Aead + AeadCore> Serializer<T> for Encrypted<S, A> {
+    fn to_bytes(&self, value: &T) -> Result<Vec<u8>, StorageError> {
+        let plaintext = self.inner.to_bytes(value)?;
+        let [low, high] = self.schema_version.to_le_bytes();
+        let prefix = [self.inner.format().id(), low, high];
+        let nonce = Nonce::<A>::try_generate().map_err(|e| StorageError::encode("Encrypted", e))?;
+        let sealed = self
+            .cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &plaintext,
+                    aad: &prefix,
+                },
+            )
+            .map_err(|e| StorageError::encode("Encrypted", e))?;
+        let mut out = Vec::with_capacity(PREFIX_LEN + nonce.len() + sealed.len());
+        out.extend_from_slice(&prefix);
+        out.extend_from_slice(&nonce);
+        out.extend_from_slice(&sealed);
+        Ok(out)
+    }
+
+    fn from_bytes(&self, bytes: &[u8]) -> Result<T, StorageError> {
+        let nonce_len = A::NonceSize::USIZE;
+        let min_len = PREFIX_LEN + nonce_len + A::TagSize::USIZE;
+        if bytes.len() < min_len {
+            return Err(StorageError::Truncated {
+                expected: min_len,
+                actual: bytes.len(),
+            });
+        }
+        let (prefix, rest) = bytes.split_at(PREFIX_LEN);
+        envelope::expect_format_id(self.inner.format(), prefix[0])?;
+        let version = u16::from_le_bytes([prefix[1], prefix[2]]);
+        if version > self.schema_version {
+            return Err(StorageError::SchemaVersionMismatch {
+                expected: self.schema_version,
+                found: version,
+            });
+        }
+        let (nonce, sealed) = rest.split_at(nonce_len);
+        let nonce = Nonce::<A>::try_from(nonce).expect("split at the nonce length");
+        let plaintext = self
+            .cipher
+            .decrypt(
+                &nonce,
+                Payload {
+                    msg: sealed,
+                    aad: prefix,
+                },
+            )
+            .map_err(|_| StorageError::AuthenticationFailed)?;
+        self.inner.from_bytes(&plaintext)
+    }
+
+    /// Seals `version` into every payload. Payloads from older versions still open, so
+    /// migrations can read them, and newer ones are rejected.
+    fn set_schema_version(&mut self, version: u16) {
+        self.schema_version = version;
+        self.inner.set_schema_version(version);
+    }
+
+    fn name(&self) -> &'static str {
+        "Encrypted"
+    }
+
+    fn format(&self) -> Format {
+        Format::Encrypted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Borsh, Json, Person, Storage, backend::Memory, load_any, migration::Migrations};
+    use aes_gcm::Aes256Gcm;
+    use chacha20poly1305::XChaCha20Poly1305;
+
+    fn person() -> Person {
+        Person {
+            color_hex: "ffffff/000000".to_string(),
+            fav_num: 6,
+        }
+    }
+
+    fn key<A: KeyInit>(byte: u8) -> Key<A> {
+        let mut key = Key::<A>::default();
+        key.fill(byte);
+        key
+    }
+
+    #[test]
+    fn round_trip_with_fresh_nonces() {
+        let mut st = Storage::new(Encrypted::<_, ChaCha20Poly1305>::new(Json, &key::<ChaCha20Poly1305>(7)));
+        st.save(&person()).unwrap();
+        let first = st.blob().unwrap().unwrap().into_owned();
+        st.save(&person()).unwrap();
+        let second = st.blob().unwrap().unwrap().into_owned();
+
+        assert_ne!(first, second);
+        assert!(!first.windows(6).any(|w| w == b"ffffff"));
+        assert_eq!(st.load().unwrap(), person());
+
+        let aes = Encrypted::<_, Aes256Gcm>::new(Borsh, &key::<Aes256Gcm>(7));
+        let bytes = aes.to_bytes(&person()).unwrap();
+        assert_eq!(Serializer::<Person>::from_bytes(&aes, &bytes).unwrap(), person());
+    }
+
+    #[test]
+    fn rejects_tampering_and_wrong_keys() {
+        let mut sealer = Encrypted::<_, XChaCha20Poly1305>::new(Borsh, &key::<XChaCha20Poly1305>(1));
+        Serializer::<Person>::set_schema_version(&mut sealer, 3);
+        let bytes = sealer.to_bytes(&person()).unwrap();
+
+        // Byte 1 turns schema version 3 into 2, which is allowed but not what was sealed.
+        for i in [1, 3, 30, bytes.len() - 1] {
+            let mut tampered = bytes.clone();
+            tampered[i] ^= 0x01;
+            assert!(matches!(
+                Serializer::<Person>::from_bytes(&sealer, &tampered),
+                Err(StorageError::AuthenticationFailed)
+            ));
+        }
+
+        let mut other = Encrypted::<_, XChaCha20Poly1305>::new(Borsh, &key::<XChaCha20Poly1305>(2));
+        Serializer::<Person>::set_schema_version(&mut other, 3);
+        assert!(matches!(
+            Serializer::<Person>::from_bytes(&other, &bytes),
+            Err(StorageError::AuthenticationFailed)
+        ));
+        assert!(matches!(
+            Serializer::<Person>::from_bytes(&sealer, &bytes[..20]),
+            Err(StorageError::Truncated { expected: 43, actual: 20 })
+        ));
+        Serializer::<Person>::set_schema_version(&mut sealer, 2);
+        assert!(matches!(
+            Serializer::<Person>::from_bytes(&sealer, &bytes),
+            Err(StorageError::SchemaVersionMismatch { expected: 2, found: 3 })
+        ));
+        assert!(matches!(
+            Serializer::<Person>::from_bytes(&Encrypted::<_, XChaCha20Poly1305>::new(Json, &key::<XChaCha20Poly1305>(1)), &bytes),
+            Err(StorageError::FormatMismatch {
+                expected: Format::Json,
+                found: Format::Borsh
+            })
+        ));
+    }
+
+    #[test]
+    fn readers_without_the_key_see_encrypted() {
+        let mut st = Storage::new(Encrypted::<_, ChaCha20Poly1305>::new(Borsh, &key::<ChaCha20Poly1305>(7)));
+        st.save(&person()).unwrap();
+        let blob = st.blob().unwrap().unwrap().into_owned();
+
+        assert!(matches!(load_any::<Person>(&blob), Err(StorageError::Encrypted)));
+        assert!(matches!(
+            Storage::<Person, _>::open(Borsh, blob.clone()),
+            Err(StorageError::Encrypted)
+        ));
+        let reopened =
+            Storage::<Person, _>::open(Encrypted::<_, ChaCha20Poly1305>::new(Borsh, &key::<ChaCha20Poly1305>(7)), blob)
+                .unwrap();
+        assert_eq!(reopened.load().unwrap(), person());
+    }
+
+    #[test]
+    fn storage_sets_the_sealed_schema_version() {
+        let encrypted = || Encrypted::<_, ChaCha20Poly1305>::new(Borsh, &key::<ChaCha20Poly1305>(7));
+        let mut st = Storage::new(encrypted()).with_schema_version(4);
+        st.save(&person()).unwrap();
+        assert_eq!(st.load().unwrap(), person());
+
+        let mut blob = st.blob().unwrap().unwrap().into_owned();
+        let reopened = Storage::<Person, _>::open(encrypted(), blob.clone()).unwrap();
+        assert_eq!(reopened.load().unwrap(), person());
+
+        // An envelope claiming version 3 would run the 3 -> 4 migration; the sealed 4 disagrees.
+        blob[6..8].copy_from_slice(&3u16.to_le_bytes());
+        let tampered = Storage::<Person, _>::with_backend(encrypted(), Memory::from(blob))
+            .with_schema_version(4)
+            .with_migrations(Migrations::new().register(3, |p: Person| p));
+        assert!(matches!(tampered.load(), Err(StorageError::AuthenticationFailed)));
+    }
+}
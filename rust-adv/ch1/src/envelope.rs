@@ -0,0 +1,190 @@
+//! Self-describing header written in front of every `Storage` payload.
+//!
+//! Layout (little endian):
+//! `magic[4] | envelope version u8 | format u8 | schema version u16 | payload len u32 | crc32 u32 | payload`
+
+use crate::error::StorageError;
+
+pub const MAGIC: [u8; 4] = *b"RADV";
+pub const VERSION: u8 = 1;
+pub const HEADER_LEN: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Format {
+    Borsh = 1,
+    Wincode = 2,
+    Json = 3,
+    Pod = 4,
+    Bincode = 5,
+    MessagePack = 6,
+    Cbor = 7,
+    Postcard = 8,
+    Anchor = 9,
+    /// `compress::Compressed`; the inner format id leads the payload.
+    Compressed = 10,
+    /// `encrypt::Encrypted`; the inner format id leads the payload.
+    Encrypted = 11,
+}
+
+impl Format {
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(Format::Borsh),
+            2 => Some(Format::Wincode),
+            3 => Some(Format::Json),
+            4 => Some(Format::Pod),
+            5 => Some(Format::Bincode),
+            6 => Some(Format::MessagePack),
+            7 => Some(Format::Cbor),
+            8 => Some(Format::Postcard),
+            9 => Some(Format::Anchor),
+            10 => Some(Format::Compressed),
+            11 => Some(Format::Encrypted),
+            _ => None,
+        }
+    }
+}
+
+/// Fails unless `found` is `expected`. Encrypted blobs opened with another serializer
+/// report `Encrypted` rather than a plain mismatch.
+pub fn expect_format(expected: Format, found: Format) -> Result<(), StorageError> {
+    match found {
+        _ if found == expected => Ok(()),
+        Format::Encrypted => Err(StorageError::Encrypted),
+        _ => Err(StorageError::FormatMismatch { expected, found }),
+    }
+}
+
+/// Like [`expect_format`], for the inner format id that wrapping serializers write
+/// at the start of their payload.
+#[cfg(any(feature = "zstd", feature = "lz4", feature = "deflate", feature = "encryption"))]
+pub(crate) fn expect_format_id(expected: Format, found: u8) -> Result<(), StorageError> {
+    expect_format(expected, Format::from_id(found).ok_or(StorageError::UnknownFormat(found))?)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    pub format: Format,
+    pub schema_version: u16,
+    pub len: u32,
+    pub checksum: u32,
+}
+
+/// Wraps `payload` in a header describing how it was encoded.
+pub fn seal(format: Format, schema_version: u16, payload: &[u8]) -> Result<Vec<u8>, StorageError> {
+    let len = u32::try_from(payload.len()).map_err(|_| StorageError::TooLarge {
+        len: payload.len(),
+        max: u32::MAX as usize,
+    })?;
+    let mut out = Vec::with_capacity(HEADER_LEN + payload.len());
+    out.extend_from_slice(&MAGIC);
+    out.push(VERSION);
+    out.push(format.id());
+    out.extend_from_slice(&schema_version.to_le_bytes());
+    out.extend_from_slice(&len.to_le_bytes());
+    out.extend_from_slice(&crc32fast::hash(payload).to_le_bytes());
+    out.extend_from_slice(payload);
+    Ok(out)
+}
+
+/// Reads the header only, without touching the payload.
+pub fn header(bytes: &[u8]) -> Result<Header, StorageError> {
+    if bytes.len() < HEADER_LEN {
+        if !MAGIC.starts_with(&bytes[..bytes.len().min(MAGIC.len())]) {
+            return Err(StorageError::BadMagic);
+        }
+        return Err(StorageError::Truncated {
+            expected: HEADER_LEN,
+            actual: bytes.len(),
+        });
+    }
+    if bytes[..4] != MAGIC {
+        return Err(StorageError::BadMagic);
+    }
+    if bytes[4] != VERSION {
+        return Err(StorageError::UnsupportedEnvelopeVersion(bytes[4]));
+    }
+    let format = Format::from_id(bytes[5]).ok_or(StorageError::UnknownFormat(bytes[5]))?;
+    Ok(Header {
+        format,
+        schema_version: u16::from_le_bytes([bytes[6], bytes[7]]),
+        len: u32::from_le_bytes(bytes[8..12].try_into().unwrap()),
+        checksum: u32::from_le_bytes(bytes[12..16].try_into().unwrap()),
+    })
+}
+
+/// Validates the header and checksum and returns the payload. `bytes` must hold
+/// exactly one envelope.
+pub fn open(bytes: &[u8]) -> Result<(Header, &[u8]), StorageError> {
+    let header = header(bytes)?;
+    let expected = HEADER_LEN + header.len as usize;
+    if bytes.len() < expected {
+        return Err(StorageError::Truncated {
+            expected,
+            actual: bytes.len(),
+        });
+    }
+    if bytes.len() > expected {
+        return Err(StorageError::TrailingBytes {
+            expected,
+            actual: bytes.len(),
+        });
+    }
+    let payload = &bytes[HEADER_LEN..expected];
+    let actual = crc32fast::hash(payload);
+    if actual != header.checksum {
+        return Err(StorageError::ChecksumMismatch {
+            expected: header.checksum,
+            actual,
+        });
+    }
+    Ok((header, payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seal_and_open() {
+        let blob = seal(Format::Wincode, 3, b"hello").unwrap();
+        assert_eq!(blob.len(), HEADER_LEN + 5);
+        let (header, payload) = open(&blob).unwrap();
+        assert_eq!(header.format, Format::Wincode);
+        assert_eq!(header.schema_version, 3);
+        assert_eq!(payload, b"hello");
+    }
+
+    #[test]
+    fn rejects_corrupted_blobs() {
+        let blob = seal(Format::Borsh, 0, b"payload").unwrap();
+
+        assert!(matches!(open(b"nope"), Err(StorageError::BadMagic)));
+        assert!(matches!(
+            open(&blob[..blob.len() - 1]),
+            Err(StorageError::Truncated { .. })
+        ));
+        let mut trailing = blob.clone();
+        trailing.push(0);
+        assert!(matches!(
+            open(&trailing),
+            Err(StorageError::TrailingBytes { expected, actual }) if actual == expected + 1
+        ));
+
+        let mut flipped = blob.clone();
+        *flipped.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            open(&flipped),
+            Err(StorageError::ChecksumMismatch { .. })
+        ));
+
+        let mut unknown = blob;
+        unknown[5] = 0xee;
+        assert!(matches!(open(&unknown), Err(StorageError::UnknownFormat(0xee))));
+    }
+}
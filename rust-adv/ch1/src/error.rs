@@ -0,0 +1,152 @@
+//! Error type shared by every `Serializer`, `Storage`, `Store` and stream.
+
+use std::{error::Error, fmt, io};
+
+use crate::envelope::Format;
+
+pub type BoxError = Box<dyn Error + Send + Sync>;
+
+#[derive(Debug)]
+pub enum StorageError {
+    /// The serializer could not encode the value.
+    Encode {
+        serializer: &'static str,
+        source: BoxError,
+    },
+    /// The payload is not valid for the serializer. `offset` is the byte where
+    /// decoding stopped, when the serializer reports one.
+    Decode {
+        serializer: &'static str,
+        offset: Option<usize>,
+        source: BoxError,
+    },
+    /// Input ended before a complete envelope or frame.
+    Truncated { expected: usize, actual: usize },
+    /// Input continues past the end of a complete envelope.
+    TrailingBytes { expected: usize, actual: usize },
+    /// Nothing has been saved yet.
+    Empty,
+    ChecksumMismatch { expected: u32, actual: u32 },
+    BadMagic,
+    UnsupportedEnvelopeVersion(u8),
+    UnknownFormat(u8),
+    FormatMismatch { expected: Format, found: Format },
+    /// Anchor account data whose 8-byte discriminator belongs to another account type.
+    DiscriminatorMismatch { expected: [u8; 8], found: [u8; 8] },
+    /// The blob was written by a newer schema than the storage knows.
+    SchemaVersionMismatch { expected: u16, found: u16 },
+    MissingMigration { from: u16 },
+    Migration {
+        from: u16,
+        source: Box<StorageError>,
+    },
+    TooLarge { len: usize, max: usize },
+    /// The version was never saved or is no longer kept in the history.
+    VersionNotFound(u64),
+    /// `rollback` found no version before the latest one in the history.
+    NoPreviousVersion,
+    /// An encrypted payload was modified, or was sealed with a different key.
+    AuthenticationFailed,
+    /// The blob is encrypted and was read without `encrypt::Encrypted`.
+    Encrypted,
+    /// A `Store` entry whose key bytes do not decode as the store's key type.
+    UndecodableStoreKey(Vec<u8>),
+    Unsupported(String),
+    Io(io::Error),
+}
+
+impl StorageError {
+    pub fn encode(serializer: &'static str, source: impl Into<BoxError>) -> Self {
+        StorageError::Encode {
+            serializer,
+            source: source.into(),
+        }
+    }
+
+    pub fn decode(serializer: &'static str, offset: Option<usize>, source: impl Into<BoxError>) -> Self {
+        StorageError::Decode {
+            serializer,
+            offset,
+            source: source.into(),
+        }
+    }
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Encode { serializer, source } => {
+                write!(f, "failed to encode with {serializer}: {source}")
+            }
+            StorageError::Decode {
+                serializer,
+                offset: Some(offset),
+                source,
+            } => write!(f, "failed to decode with {serializer} at byte {offset}: {source}"),
+            StorageError::Decode {
+                serializer, source, ..
+            } => write!(f, "failed to decode with {serializer}: {source}"),
+            StorageError::Truncated { expected, actual } => {
+                write!(f, "input truncated: expected {expected} bytes, got {actual}")
+            }
+            StorageError::TrailingBytes { expected, actual } => {
+                write!(f, "{} unexpected bytes after a {expected}-byte envelope", actual - expected)
+            }
+            StorageError::Empty => write!(f, "no data"),
+            StorageError::ChecksumMismatch { expected, actual } => {
+                write!(f, "checksum mismatch: expected {expected:#010x}, got {actual:#010x}")
+            }
+            StorageError::BadMagic => write!(f, "not a storage envelope (bad magic)"),
+            StorageError::UnsupportedEnvelopeVersion(v) => write!(f, "unsupported envelope version {v}"),
+            StorageError::UnknownFormat(id) => write!(f, "unknown serializer id {id}"),
+            StorageError::FormatMismatch { expected, found } => {
+                write!(f, "payload is {found:?}, expected {expected:?}")
+            }
+            StorageError::DiscriminatorMismatch { expected, found } => {
+                write!(f, "account discriminator {found:02x?}, expected {expected:02x?}")
+            }
+            StorageError::SchemaVersionMismatch { expected, found } => {
+                write!(f, "payload has schema version {found}, expected at most {expected}")
+            }
+            StorageError::MissingMigration { from } => {
+                write!(f, "no migration registered from schema version {from}")
+            }
+            StorageError::Migration { from, source } => {
+                write!(f, "migration from schema version {from} failed: {source}")
+            }
+            StorageError::TooLarge { len, max } => {
+                write!(f, "{len} bytes exceeds the limit of {max}")
+            }
+            StorageError::VersionNotFound(n) => write!(f, "version {n} is not in the history"),
+            StorageError::NoPreviousVersion => write!(f, "no earlier version is kept to roll back to"),
+            StorageError::AuthenticationFailed => {
+                write!(f, "payload failed authentication (tampered or wrong key)")
+            }
+            StorageError::Encrypted => write!(f, "payload is encrypted; open it with the key"),
+            StorageError::UndecodableStoreKey(bytes) => {
+                write!(f, "store key {bytes:02x?} does not decode as the key type")
+            }
+            StorageError::Unsupported(what) => write!(f, "unsupported: {what}"),
+            StorageError::Io(e) => write!(f, "io error: {e}"),
+        }
+    }
+}
+
+impl Error for StorageError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StorageError::Encode { source, .. } | StorageError::Decode { source, .. } => {
+                Some(source.as_ref())
+            }
+            StorageError::Migration { source, .. } => Some(source.as_ref()),
+            StorageError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for StorageError {
+    fn from(e: io::Error) -> Self {
+        StorageError::Io(e)
+    }
+}
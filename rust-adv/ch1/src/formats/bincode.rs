@@ -0,0 +1,64 @@
+use std::{
+    fmt::Debug as db,
+    io::{Read, Write},
+};
+
+use bincode::error::{DecodeError, EncodeError};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{Serializer, envelope::Format, error::StorageError};
+
+/// Most bytes a single decode may allocate. Without a limit bincode trusts length
+/// prefixes, so one corrupt byte can ask for exabytes and abort the process.
+const DECODE_LIMIT: usize = 256 << 20;
+
+/// bincode 2 with its `standard` config (varint integers).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bincode;
+
+fn config() -> impl bincode::config::Config {
+    bincode::config::standard().with_limit::<DECODE_LIMIT>()
+}
+
+impl<T: db + Serialize + DeserializeOwned> Serializer<T> for Bincode {
+    fn to_bytes(&self, value: &T) -> Result<Vec<u8>, StorageError> {
+        bincode::serde::encode_to_vec(value, config())
+            .map_err(|e| StorageError::encode("Bincode", e))
+    }
+
+    fn from_bytes(&self, bytes: &[u8]) -> Result<T, StorageError> {
+        let (value, read) = bincode::serde::decode_from_slice(bytes, config())
+            .map_err(|e| StorageError::decode("Bincode", None, e))?;
+        if read != bytes.len() {
+            return Err(StorageError::decode(
+                "Bincode",
+                Some(read),
+                "trailing bytes after value",
+            ));
+        }
+        Ok(value)
+    }
+
+    fn serialize_into(&self, value: &T, writer: &mut impl Write) -> Result<(), StorageError> {
+        bincode::serde::encode_into_std_write(value, writer, config()).map_err(|e| match e {
+            EncodeError::Io { inner, .. } => StorageError::Io(inner),
+            e => StorageError::encode("Bincode", e),
+        })?;
+        Ok(())
+    }
+
+    fn deserialize_from(&self, reader: &mut impl Read) -> Result<T, StorageError> {
+        bincode::serde::decode_from_std_read(reader, config()).map_err(|e| match e {
+            DecodeError::Io { inner, .. } => StorageError::Io(inner),
+            e => StorageError::decode("Bincode", None, e),
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "Bincode"
+    }
+
+    fn format(&self) -> Format {
+        Format::Bincode
+    }
+}
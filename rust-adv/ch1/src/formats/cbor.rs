@@ -0,0 +1,62 @@
+use std::{
+    fmt::Debug as db,
+    io::{Read, Write},
+};
+
+use ciborium::de::Error as DeError;
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{Serializer, envelope::Format, error::StorageError};
+
+/// CBOR via ciborium.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cbor;
+
+impl<T: db + Serialize + DeserializeOwned> Serializer<T> for Cbor {
+    fn to_bytes(&self, value: &T) -> Result<Vec<u8>, StorageError> {
+        let mut out = Vec::new();
+        ciborium::into_writer(value, &mut out).map_err(|e| StorageError::encode("CBOR", e))?;
+        Ok(out)
+    }
+
+    fn from_bytes(&self, bytes: &[u8]) -> Result<T, StorageError> {
+        let mut rest = bytes;
+        let value = ciborium::from_reader(&mut rest).map_err(decode_error)?;
+        if !rest.is_empty() {
+            return Err(StorageError::decode(
+                "CBOR",
+                Some(bytes.len() - rest.len()),
+                "trailing bytes after value",
+            ));
+        }
+        Ok(value)
+    }
+
+    fn serialize_into(&self, value: &T, writer: &mut impl Write) -> Result<(), StorageError> {
+        ciborium::into_writer(value, writer).map_err(|e| match e {
+            ciborium::ser::Error::Io(e) => StorageError::Io(e),
+            e => StorageError::encode("CBOR", e),
+        })
+    }
+
+    fn deserialize_from(&self, reader: &mut impl Read) -> Result<T, StorageError> {
+        ciborium::from_reader(reader).map_err(decode_error)
+    }
+
+    fn name(&self) -> &'static str {
+        "CBOR"
+    }
+
+    fn format(&self) -> Format {
+        Format::Cbor
+    }
+}
+
+fn decode_error(e: DeError<std::io::Error>) -> StorageError {
+    match e {
+        DeError::Io(e) => StorageError::Io(e),
+        DeError::Syntax(offset) => StorageError::decode("CBOR", Some(offset), "syntax error"),
+        DeError::Semantic(offset, msg) => StorageError::decode("CBOR", offset, msg),
+        e => StorageError::decode("CBOR", None, e),
+    }
+}
@@ -0,0 +1,61 @@
+use std::{
+    fmt::Debug as db,
+    io::{Read, Write},
+};
+
+use rmp::encode::ValueWriteError;
+use rmp_serde::{decode, encode};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{Serializer, envelope::Format, error::StorageError};
+
+/// MessagePack via rmp-serde, structs encoded as arrays.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MessagePack;
+
+impl<T: db + Serialize + DeserializeOwned> Serializer<T> for MessagePack {
+    fn to_bytes(&self, value: &T) -> Result<Vec<u8>, StorageError> {
+        rmp_serde::to_vec(value).map_err(|e| StorageError::encode("MessagePack", e))
+    }
+
+    fn from_bytes(&self, bytes: &[u8]) -> Result<T, StorageError> {
+        let mut rest = bytes;
+        let value = decode::from_read(&mut rest).map_err(decode_error)?;
+        if !rest.is_empty() {
+            return Err(StorageError::decode(
+                "MessagePack",
+                Some(bytes.len() - rest.len()),
+                "trailing bytes after value",
+            ));
+        }
+        Ok(value)
+    }
+
+    fn serialize_into(&self, value: &T, writer: &mut impl Write) -> Result<(), StorageError> {
+        encode::write(writer, value).map_err(|e| match e {
+            encode::Error::InvalidValueWrite(
+                ValueWriteError::InvalidMarkerWrite(e) | ValueWriteError::InvalidDataWrite(e),
+            ) => StorageError::Io(e),
+            e => StorageError::encode("MessagePack", e),
+        })
+    }
+
+    fn deserialize_from(&self, reader: &mut impl Read) -> Result<T, StorageError> {
+        decode::from_read(reader).map_err(decode_error)
+    }
+
+    fn name(&self) -> &'static str {
+        "MessagePack"
+    }
+
+    fn format(&self) -> Format {
+        Format::MessagePack
+    }
+}
+
+fn decode_error(e: decode::Error) -> StorageError {
+    match e {
+        decode::Error::InvalidMarkerRead(e) | decode::Error::InvalidDataRead(e) => StorageError::Io(e),
+        e => StorageError::decode("MessagePack", None, e),
+    }
+}
@@ -0,0 +1,44 @@
+use std::{fmt::Debug as db, io::Write};
+
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{Serializer, envelope::Format, error::StorageError};
+
+/// postcard, the varint format used by embedded Rust.
+///
+/// `deserialize_from` keeps the read-to-EOF default: postcard's `from_io` needs a scratch
+/// buffer as large as the longest string in `T`. Read several values through `FrameReader`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Postcard;
+
+impl<T: db + Serialize + DeserializeOwned> Serializer<T> for Postcard {
+    fn to_bytes(&self, value: &T) -> Result<Vec<u8>, StorageError> {
+        postcard::to_stdvec(value).map_err(|e| StorageError::encode("Postcard", e))
+    }
+
+    fn from_bytes(&self, bytes: &[u8]) -> Result<T, StorageError> {
+        let (value, rest) =
+            postcard::take_from_bytes(bytes).map_err(|e| StorageError::decode("Postcard", None, e))?;
+        if !rest.is_empty() {
+            return Err(StorageError::decode(
+                "Postcard",
+                Some(bytes.len() - rest.len()),
+                "trailing bytes after value",
+            ));
+        }
+        Ok(value)
+    }
+
+    fn serialize_into(&self, value: &T, writer: &mut impl Write) -> Result<(), StorageError> {
+        postcard::to_io(value, writer).map_err(|e| StorageError::encode("Postcard", e))?;
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "Postcard"
+    }
+
+    fn format(&self) -> Format {
+        Format::Postcard
+    }
+}
@@ -0,0 +1,219 @@
+//! Previous versions kept by `Storage::with_history`, and field-level diffs between them.
+//!
+//! Every save is numbered, starting at 1, and the last `limit` saves are kept as
+//! enveloped blobs. Older ones are dropped. Diffs decode both versions into `T` and
+//! compare them field by field through their serde representation.
+
+use std::{collections::VecDeque, fmt, time::SystemTime};
+
+use serde_json::Value;
+
+/// One saved version.
+#[derive(Debug, Clone)]
+pub struct Version {
+    pub number: u64,
+    pub saved_at: SystemTime,
+    blob: Vec<u8>,
+}
+
+impl Version {
+    /// The enveloped bytes written by that save.
+    pub fn blob(&self) -> &[u8] {
+        &self.blob
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct History {
+    limit: usize,
+    versions: VecDeque<Version>,
+    saves: u64,
+}
+
+impl History {
+    pub(crate) fn new(limit: usize) -> Self {
+        History {
+            limit,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.limit > 0
+    }
+
+    pub(crate) fn push(&mut self, blob: Vec<u8>) {
+        self.saves += 1;
+        if self.versions.len() == self.limit {
+            self.versions.pop_front();
+        }
+        self.versions.push_back(Version {
+            number: self.saves,
+            saved_at: SystemTime::now(),
+            blob,
+        });
+    }
+
+    pub(crate) fn get(&self, number: u64) -> Option<&Version> {
+        self.versions.iter().find(|v| v.number == number)
+    }
+
+    /// The version before the latest, if one is kept.
+    pub(crate) fn previous(&self) -> Option<&Version> {
+        self.versions.iter().nth_back(1)
+    }
+
+    pub(crate) fn discard_latest(&mut self) {
+        self.versions.pop_back();
+    }
+
+    pub(crate) fn iter(&self) -> impl DoubleEndedIterator<Item = &Version> {
+        self.versions.iter()
+    }
+}
+
+/// Two versions decoded into `T`, and what changed between them.
+#[derive(Debug)]
+pub struct VersionDiff<T> {
+    pub before: T,
+    pub after: T,
+    pub changes: Vec<FieldChange>,
+}
+
+/// A value at `path` (`accounts[3].lamports`) that differs. `None` on one side
+/// means the field or element is missing there.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    pub path: String,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+impl fmt::Display for FieldChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |v: &Option<Value>| v.as_ref().map_or_else(|| "-".to_string(), Value::to_string);
+        write!(f, "{}: {} -> {}", self.path, show(&self.before), show(&self.after))
+    }
+}
+
+/// Leaf-level differences between two serde values. `serde_json` keeps object keys
+/// sorted, so fields come in alphabetical order rather than declaration order.
+pub(crate) fn diff_values(before: &Value, after: &Value) -> Vec<FieldChange> {
+    let mut changes = Vec::new();
+    walk(String::new(), Some(before), Some(after), &mut changes);
+    changes
+}
+
+fn child(path: &str, key: &str) -> String {
+    match path.is_empty() {
+        true => key.to_string(),
+        false => format!("{path}.{key}"),
+    }
+}
+
+fn walk(path: String, before: Option<&Value>, after: Option<&Value>, changes: &mut Vec<FieldChange>) {
+    match (before, after) {
+        (Some(Value::Object(a)), Some(Value::Object(b))) => {
+            for (key, value) in a {
+                walk(child(&path, key), Some(value), b.get(key), changes);
+            }
+            for (key, value) in b.iter().filter(|(key, _)| !a.contains_key(*key)) {
+                walk(child(&path, key), None, Some(value), changes);
+            }
+        }
+        (Some(Value::Array(a)), Some(Value::Array(b))) => {
+            for i in 0..a.len().max(b.len()) {
+                walk(format!("{path}[{i}]"), a.get(i), b.get(i), changes);
+            }
+        }
+        (a, b) if a != b => changes.push(FieldChange {
+            path,
+            before: a.cloned(),
+            after: b.cloned(),
+        }),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Borsh, Json, Person, Storage, error::StorageError};
+
+    fn person(color_hex: &str, fav_num: u64) -> Person {
+        Person {
+            color_hex: color_hex.to_string(),
+            fav_num,
+        }
+    }
+
+    #[test]
+    fn keeps_the_last_n_versions() {
+        let mut st = Storage::new(Borsh).with_history(3);
+        for n in 1..=5 {
+            st.save(&person("fff", n)).unwrap();
+        }
+        let numbers: Vec<_> = st.versions().map(|v| v.number).collect();
+        assert_eq!(numbers, [3, 4, 5]);
+        assert!(st.versions().all(|v| v.saved_at <= SystemTime::now()));
+        assert_eq!(st.load_version(4).unwrap(), person("fff", 4));
+        assert!(matches!(st.load_version(2), Err(StorageError::VersionNotFound(2))));
+
+        let plain = Storage::<Person, _>::new(Borsh);
+        assert_eq!(plain.versions().count(), 0);
+        assert!(matches!(plain.load_version(1), Err(StorageError::VersionNotFound(1))));
+    }
+
+    #[test]
+    fn rollback_restores_the_previous_version() {
+        let mut st = Storage::new(Json).with_history(2);
+        st.save(&person("aaa", 1)).unwrap();
+        st.save(&person("bbb", 2)).unwrap();
+        st.save(&person("ccc", 3)).unwrap();
+
+        assert_eq!(st.rollback().unwrap(), person("bbb", 2));
+        assert_eq!(st.load().unwrap(), person("bbb", 2));
+        // Version 1 was already dropped, so there is nothing further back.
+        assert!(matches!(st.rollback(), Err(StorageError::NoPreviousVersion)));
+        assert_eq!(st.load().unwrap(), person("bbb", 2));
+
+        // Numbering continues after a rollback.
+        st.save(&person("ddd", 4)).unwrap();
+        let numbers: Vec<_> = st.versions().map(|v| v.number).collect();
+        assert_eq!(numbers, [2, 4]);
+    }
+
+    #[test]
+    fn diff_between_versions() {
+        let mut st = Storage::new(Borsh).with_history(4);
+        st.save(&person("ffffff", 6)).unwrap();
+        st.save(&person("000000", 6)).unwrap();
+        st.save(&person("000000", 7)).unwrap();
+
+        let diff = st.diff(1, 3).unwrap();
+        assert_eq!(diff.before, person("ffffff", 6));
+        assert_eq!(diff.after, person("000000", 7));
+        let lines: Vec<_> = diff.changes.iter().map(ToString::to_string).collect();
+        assert_eq!(lines, [r#"color_hex: "ffffff" -> "000000""#, "fav_num: 6 -> 7"]);
+        assert!(st.diff(2, 2).unwrap().changes.is_empty());
+        assert!(matches!(st.diff(0, 2), Err(StorageError::VersionNotFound(0))));
+    }
+
+    #[test]
+    fn nested_and_list_changes() {
+        let before = serde_json::json!({ "slot": 1, "accounts": [{ "lamports": 5 }, { "lamports": 6 }] });
+        let after = serde_json::json!({ "slot": 1, "accounts": [{ "lamports": 9 }], "label": "x" });
+        let paths: Vec<_> = diff_values(&before, &after)
+            .into_iter()
+            .map(|c| (c.path, c.before.is_some(), c.after.is_some()))
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                ("accounts[0].lamports".to_string(), true, true),
+                ("accounts[1]".to_string(), true, false),
+                ("label".to_string(), false, true),
+            ]
+        );
+    }
+}
@@ -0,0 +1,730 @@
+//! One `Storage` API over Borsh, Wincode, JSON and the optional serde formats.
+//!
+//! Other crates depend on it with `ch1 = { path = "../ch1", default-features = false }`;
+//! the default `cli` feature only adds the `ch1` binary (convert, inspect, bench).
+
+// Lets `#[storable]` expand to `::ch1::...` paths inside this crate too.
+extern crate self as ch1;
+
+#[cfg(feature = "anchor")]
+pub mod anchor;
+#[cfg(feature = "tokio")]
+pub mod async_storage;
+pub mod backend;
+#[cfg(any(feature = "zstd", feature = "lz4", feature = "deflate"))]
+pub mod compress;
+#[cfg(feature = "encryption")]
+pub mod encrypt;
+pub mod envelope;
+pub mod error;
+pub mod formats;
+pub mod history;
+pub mod migration;
+pub mod observe;
+#[cfg(test)]
+mod proptests;
+pub mod schema;
+pub mod store;
+pub mod stream;
+pub mod zero_copy;
+
+use std::{ borrow::Cow, io::{self, Read, Write}, fmt::Debug as db, marker::PhantomData, time::Instant};
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::{Serialize, de::DeserializeOwned};
+use wincode::{SchemaRead, SchemaWrite, config::DefaultConfig};
+
+use backend::{Backend, Memory};
+use envelope::{Format, Header};
+use error::StorageError;
+use history::{History, Version, VersionDiff};
+use migration::Migrations;
+use observe::{Event, Observer, Operation};
+pub use schema::storable;
+use stream::{WincodeReader, WincodeWriter};
+use zero_copy::BorrowedSerializer;
+
+pub trait Serializer<T: db> {
+    fn name(&self) -> &'static str;
+    fn format(&self) -> Format;
+    fn to_bytes(&self, value: &T) -> Result<Vec<u8>, StorageError>;
+    #[allow(clippy::wrong_self_convention)]
+    fn from_bytes(&self, bytes: &[u8]) -> Result<T, StorageError>;
+
+    /// Receives the schema version whenever `Storage` sets it. Serializers that seal the
+    /// version into their payload, like `Encrypted`, keep it; the rest ignore it.
+    fn set_schema_version(&mut self, _version: u16) {}
+
+    /// Encodes `value` straight into `writer`. Buffers through `to_bytes` unless overridden.
+    fn serialize_into(&self, value: &T, writer: &mut impl Write) -> Result<(), StorageError>
+    where
+        Self: Sized,
+    {
+        writer.write_all(&self.to_bytes(value)?)?;
+        Ok(())
+    }
+
+    /// Decodes one value from `reader`. The default reads to EOF and calls `from_bytes`;
+    /// use [`stream::FrameReader`] to read several values from one stream.
+    fn deserialize_from(&self, reader: &mut impl Read) -> Result<T, StorageError>
+    where
+        Self: Sized,
+    {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        self.from_bytes(&bytes)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Borsh;
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Wincode;
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json;
+
+impl<T: db + BorshDeserialize + BorshSerialize> Serializer<T> for Borsh {
+    fn to_bytes(&self, value: &T) -> Result<Vec<u8>, StorageError> {
+        borsh::to_vec(value).map_err(|e| StorageError::encode("Borsh", e))
+    }
+
+    fn from_bytes(&self, bytes: &[u8]) -> Result<T, StorageError> {
+        let mut rest = bytes;
+        let value = T::deserialize(&mut rest)
+            .map_err(|e| StorageError::decode("Borsh", Some(bytes.len() - rest.len()), e))?;
+        if !rest.is_empty() {
+            return Err(StorageError::decode(
+                "Borsh",
+                Some(bytes.len() - rest.len()),
+                "trailing bytes after value",
+            ));
+        }
+        Ok(value)
+    }
+
+    fn serialize_into(&self, value: &T, writer: &mut impl Write) -> Result<(), StorageError> {
+        value.serialize(writer).map_err(StorageError::Io)
+    }
+
+    /// Reads exactly one value and leaves the rest of the stream untouched.
+    fn deserialize_from(&self, reader: &mut impl Read) -> Result<T, StorageError> {
+        T::deserialize_reader(reader).map_err(|e| match e.kind() {
+            io::ErrorKind::InvalidData => StorageError::decode("Borsh", None, e),
+            _ => StorageError::Io(e),
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "Borsh"
+    }
+
+    fn format(&self) -> Format {
+        Format::Borsh
+    }
+}
+
+/// Maps a serde_json error to a byte offset using the line/column it reports.
+fn json_error(bytes: Option<&[u8]>, e: serde_json::Error) -> StorageError {
+    if e.is_io() {
+        return StorageError::Io(e.into());
+    }
+    let offset = bytes.filter(|_| e.line() > 0).map(|bytes| {
+        let line_start: usize = bytes
+            .split(|b| *b == b'\n')
+            .take(e.line() - 1)
+            .map(|line| line.len() + 1)
+            .sum();
+        (line_start + e.column().saturating_sub(1)).min(bytes.len())
+    });
+    StorageError::decode("Serde_Json", offset, e)
+}
+
+impl<T: db + Serialize + DeserializeOwned> Serializer<T> for Json {
+    fn to_bytes(&self, value: &T) -> Result<Vec<u8>, StorageError> {
+        serde_json::to_vec(value).map_err(|e| StorageError::encode("Serde_Json", e))
+    }
+
+    fn from_bytes(&self, bytes: &[u8]) -> Result<T, StorageError> {
+        serde_json::from_slice(bytes).map_err(|e| json_error(Some(bytes), e))
+    }
+
+    fn serialize_into(&self, value: &T, writer: &mut impl Write) -> Result<(), StorageError> {
+        serde_json::to_writer(writer, value).map_err(|e| match e.is_io() {
+            true => StorageError::Io(e.into()),
+            false => StorageError::encode("Serde_Json", e),
+        })
+    }
+
+    /// Reads exactly one value. Objects, arrays and strings end on their closing
+    /// delimiter; a bare number or literal also consumes the byte after it.
+    fn deserialize_from(&self, reader: &mut impl Read) -> Result<T, StorageError> {
+        match serde_json::Deserializer::from_reader(reader).into_iter().next() {
+            Some(value) => value.map_err(|e| json_error(None, e)),
+            None => Err(StorageError::Io(io::ErrorKind::UnexpectedEof.into())),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "Serde_Json"
+    }
+
+    fn format(&self) -> Format {
+        Format::Json
+    }
+}
+
+impl<T: db + SchemaWrite<DefaultConfig, Src = T> + for<'de> SchemaRead<'de, DefaultConfig, Dst = T>>
+    Serializer<T> for Wincode
+{
+    fn to_bytes(&self, value: &T) -> Result<Vec<u8>, StorageError> {
+        wincode::serialize(value).map_err(|e| StorageError::encode("WinCode", e))
+    }
+
+    fn from_bytes(&self, bytes: &[u8]) -> Result<T, StorageError> {
+        wincode::deserialize(bytes).map_err(|e| StorageError::decode("WinCode", None, e))
+    }
+
+    fn serialize_into(&self, value: &T, writer: &mut impl Write) -> Result<(), StorageError> {
+        wincode::serialize_into(WincodeWriter(writer), value).map_err(|e| match e {
+            wincode::WriteError::Io(wincode::io::WriteError::Io(e)) => StorageError::Io(e),
+            e => StorageError::encode("WinCode", e),
+        })
+    }
+
+    /// Reads exactly one value and leaves the rest of the stream untouched.
+    fn deserialize_from(&self, reader: &mut impl Read) -> Result<T, StorageError> {
+        wincode::deserialize_from(WincodeReader::new(reader)).map_err(|e| match e {
+            wincode::ReadError::Io(wincode::io::ReadError::Io(e)) => StorageError::Io(e),
+            wincode::ReadError::Io(wincode::io::ReadError::ReadSizeLimit(_)) => {
+                StorageError::Io(io::ErrorKind::UnexpectedEof.into())
+            }
+            e => StorageError::decode("WinCode", None, e),
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "WinCode"
+    }
+
+    fn format(&self) -> Format {
+        Format::Wincode
+    }
+}
+
+pub struct Storage<T, S, B = Memory> {
+    backend: B,
+    serializer: S,
+    schema_version: u16,
+    migrations: Migrations<S>,
+    observer: Option<Box<dyn Observer>>,
+    history: History,
+    _type: PhantomData<T>,
+}
+
+impl<T: db, S: Serializer<T>> Storage<T, S> {
+    pub fn new(serializer: S) -> Self {
+        Self::with_backend(serializer, Memory::default())
+    }
+
+    /// Restores an in-memory storage from a blob previously returned by [`Storage::blob`].
+    pub fn open(serializer: S, blob: Vec<u8>) -> Result<Self, StorageError> {
+        let header = envelope::open(&blob)?.0;
+        envelope::expect_format(serializer.format(), header.format)?;
+        Ok(Self::with_backend(serializer, Memory::from(blob)).with_schema_version(header.schema_version))
+    }
+}
+
+impl<T: db, S: Serializer<T>, B: Backend> Storage<T, S, B> {
+    pub fn with_backend(serializer: S, backend: B) -> Self {
+        Storage {
+            backend,
+            serializer,
+            schema_version: 0,
+            migrations: Migrations::new(),
+            observer: None,
+            history: History::default(),
+            _type: PhantomData,
+        }
+    }
+
+    /// Schema version of `T` stamped into every saved envelope.
+    pub fn with_schema_version(mut self, version: u16) -> Self {
+        self.schema_version = version;
+        self.serializer.set_schema_version(version);
+        self
+    }
+
+    /// Upgrades applied on `load` to blobs stamped with an older schema version.
+    pub fn with_migrations(mut self, migrations: Migrations<S>) -> Self {
+        self.migrations = migrations;
+        self
+    }
+
+    /// Reports every `save` / `load` to `observer`. Nothing is timed or reported without one.
+    pub fn with_observer(mut self, observer: impl Observer + 'static) -> Self {
+        self.observer = Some(Box::new(observer));
+        self
+    }
+
+    /// Keeps the last `limit` saved versions, numbered from 1, for
+    /// [`Storage::load_version`], [`Storage::rollback`] and [`Storage::diff`].
+    pub fn with_history(mut self, limit: usize) -> Self {
+        self.history = History::new(limit);
+        self
+    }
+
+    pub fn save(&mut self, value: &T) -> Result<(), StorageError> {
+        let start = self.observer.as_ref().map(|_| Instant::now());
+        let result = self.save_payload(value);
+        self.report(Operation::Save, start, result.as_ref().map(|len| *len));
+        result.map(|_| ())
+    }
+
+    fn save_payload(&mut self, value: &T) -> Result<usize, StorageError> {
+        let bytes = self.serializer.to_bytes(value)?;
+        let blob = envelope::seal(self.serializer.format(), self.schema_version, &bytes)?;
+        self.backend.write(&blob)?;
+        if self.history.is_enabled() {
+            self.history.push(blob);
+        }
+        Ok(bytes.len())
+    }
+
+    pub fn load(&self) -> Result<T, StorageError> {
+        let start = self.observer.as_ref().map(|_| Instant::now());
+        let result = self.load_payload();
+        self.report(Operation::Load, start, result.as_ref().map(|(_, len)| *len));
+        result.map(|(value, _)| value)
+    }
+
+    fn load_payload(&self) -> Result<(T, usize), StorageError> {
+        match self.backend.read()? {
+            Some(data) => {
+                let payload = self.payload(&data)?;
+                Ok((self.serializer.from_bytes(&payload)?, payload.len()))
+            }
+            None => Err(StorageError::Empty),
+        }
+    }
+
+    fn report(&self, op: Operation, start: Option<Instant>, result: Result<usize, &StorageError>) {
+        if let (Some(observer), Some(start)) = (&self.observer, start) {
+            observer.observe(&Event {
+                op,
+                serializer: self.serializer.name(),
+                bytes: *result.as_ref().unwrap_or(&0),
+                duration: start.elapsed(),
+                error: result.err(),
+            });
+        }
+    }
+
+    /// Versions kept by [`Storage::with_history`], oldest first.
+    pub fn versions(&self) -> impl DoubleEndedIterator<Item = &Version> {
+        self.history.iter()
+    }
+
+    pub fn load_version(&self, number: u64) -> Result<T, StorageError> {
+        let version = self.history.get(number).ok_or(StorageError::VersionNotFound(number))?;
+        self.serializer.from_bytes(&self.payload(version.blob())?)
+    }
+
+    /// Discards the latest version and writes the one before it back to the backend.
+    /// Fails with `NoPreviousVersion` when no earlier version is kept.
+    pub fn rollback(&mut self) -> Result<T, StorageError> {
+        let previous = self.history.previous().ok_or(StorageError::NoPreviousVersion)?;
+        let value = self.serializer.from_bytes(&self.payload(previous.blob())?)?;
+        self.backend.write(previous.blob())?;
+        self.history.discard_latest();
+        Ok(value)
+    }
+
+    pub fn has_data(&self) -> bool {
+        self.backend.has_data()
+    }
+
+    /// Decodes a view that borrows from the stored blob instead of allocating.
+    /// Needs a backend that hands out borrowed bytes (`Memory`, `MmapFile`) and a
+    /// blob already at the current schema version.
+    pub fn load_borrowed<'a, U: 'a>(&'a self) -> Result<U, StorageError>
+    where
+        S: BorrowedSerializer<'a, U>,
+    {
+        match self.backend.read()? {
+            Some(Cow::Borrowed(data)) => match self.payload(data)? {
+                Cow::Borrowed(payload) => self.serializer.from_bytes_borrowed(payload),
+                Cow::Owned(_) => Err(StorageError::Unsupported(
+                    "borrowed reads of blobs that need migration".to_string(),
+                )),
+            },
+            Some(Cow::Owned(_)) => Err(StorageError::Unsupported(
+                "borrowed reads from this backend".to_string(),
+            )),
+            None => Err(StorageError::Empty),
+        }
+    }
+
+    /// The enveloped bytes as they would be written to a file.
+    pub fn blob(&self) -> Result<Option<Cow<'_, [u8]>>, StorageError> {
+        Ok(self.backend.read()?)
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    fn payload<'a>(&self, data: &'a [u8]) -> Result<Cow<'a, [u8]>, StorageError> {
+        current_payload(
+            data,
+            &self.serializer,
+            self.serializer.format(),
+            self.schema_version,
+            &self.migrations,
+        )
+    }
+
+    /// Decodes versions `from` and `to` and lists the fields that differ.
+    pub fn diff(&self, from: u64, to: u64) -> Result<VersionDiff<T>, StorageError>
+    where
+        T: Serialize,
+    {
+        let before = self.load_version(from)?;
+        let after = self.load_version(to)?;
+        let as_value = |value: &T| serde_json::to_value(value).map_err(|e| StorageError::encode("Serde_Json", e));
+        let changes = history::diff_values(&as_value(&before)?, &as_value(&after)?);
+        Ok(VersionDiff { before, after, changes })
+    }
+
+    /// Re-encodes the stored payload with `serializer` into a new in-memory storage.
+    /// An empty storage converts to an empty one.
+    pub fn convert<S2: Serializer<T>>(&self, serializer: S2) -> Result<Storage<T, S2>, StorageError> {
+        self.convert_into(serializer, Memory::default())
+    }
+
+    /// Like [`Storage::convert`], but writes the re-encoded blob to `backend`.
+    pub fn convert_into<S2: Serializer<T>, B2: Backend>(
+        &self,
+        mut serializer: S2,
+        mut backend: B2,
+    ) -> Result<Storage<T, S2, B2>, StorageError> {
+        serializer.set_schema_version(self.schema_version);
+        if let Some(data) = self.backend.read()? {
+            let payload = self.payload(&data)?;
+            let bytes = convert(&self.serializer, &serializer, &payload)?;
+            backend.write(&envelope::seal(serializer.format(), self.schema_version, &bytes)?)?;
+        }
+        Ok(Storage::with_backend(serializer, backend).with_schema_version(self.schema_version))
+    }
+}
+
+/// Opens the envelope in `data`, checks it against `format` and `schema_version`, and
+/// runs `migrations` on payloads stamped with an older version.
+pub(crate) fn current_payload<'a, S>(
+    data: &'a [u8],
+    serializer: &S,
+    format: Format,
+    schema_version: u16,
+    migrations: &Migrations<S>,
+) -> Result<Cow<'a, [u8]>, StorageError> {
+    let (header, payload) = envelope::open(data)?;
+    envelope::expect_format(format, header.format)?;
+    #[cfg(feature = "encryption")]
+    if header.format == Format::Encrypted
+        && encrypt::sealed_schema_version(payload).is_some_and(|sealed| sealed != header.schema_version)
+    {
+        return Err(StorageError::AuthenticationFailed);
+    }
+    if header.schema_version > schema_version {
+        return Err(StorageError::SchemaVersionMismatch {
+            expected: schema_version,
+            found: header.schema_version,
+        });
+    }
+    migrations.upgrade(serializer, header.schema_version, schema_version, payload)
+}
+
+/// Decodes a blob written by any serializer except `Pod`, picking the one named in its header.
+/// Formats whose cargo feature is disabled are reported as errors.
+pub fn load_any<T>(blob: &[u8]) -> Result<(Header, T), StorageError>
+where
+    T: db
+        + BorshSerialize
+        + BorshDeserialize
+        + Serialize
+        + DeserializeOwned
+        + SchemaWrite<DefaultConfig, Src = T>
+        + for<'de> SchemaRead<'de, DefaultConfig, Dst = T>,
+{
+    let (header, payload) = envelope::open(blob)?;
+    let value = match header.format {
+        Format::Borsh => Borsh.from_bytes(payload)?,
+        Format::Wincode => Wincode.from_bytes(payload)?,
+        Format::Json => Json.from_bytes(payload)?,
+        #[cfg(feature = "bincode")]
+        Format::Bincode => formats::Bincode.from_bytes(payload)?,
+        #[cfg(feature = "msgpack")]
+        Format::MessagePack => formats::MessagePack.from_bytes(payload)?,
+        #[cfg(feature = "cbor")]
+        Format::Cbor => formats::Cbor.from_bytes(payload)?,
+        #[cfg(feature = "postcard")]
+        Format::Postcard => formats::Postcard.from_bytes(payload)?,
+        Format::Encrypted => return Err(StorageError::Encrypted),
+        format => {
+            return Err(StorageError::Unsupported(format!(
+                "load_any of {format:?} payloads"
+            )));
+        }
+    };
+    Ok((header, value))
+}
+
+//\/\/\/\/\/ convert \/\/\/\/\/
+
+/// Decodes `bytes` with `from` and encodes the value again with `to`.
+pub fn convert<T: db, A: Serializer<T>, B: Serializer<T>>(
+    from: &A,
+    to: &B,
+    bytes: &[u8],
+) -> Result<Vec<u8>, StorageError> {
+    to.to_bytes(&from.from_bytes(bytes)?)
+}
+
+#[storable]
+#[derive(PartialEq, Debug)]
+pub struct Person {
+    pub color_hex: String,
+    pub fav_num: u64,
+}
+
+//\/\/\/\/\/\/\/\Test/\/\/\/\/\/\/\
+
+#[test]
+#[allow(clippy::bool_assert_comparison)]
+pub fn for_borsh() {
+    let per = Person {
+        color_hex: "ffffff/000000".to_string(),
+        fav_num: 6,
+    };
+    let mut st = Storage::new(Borsh);
+    assert_eq!(st.has_data(), false);
+    st.save(&per).unwrap();
+    assert_eq!(st.load().unwrap(), per)
+}
+
+#[test]
+#[allow(clippy::bool_assert_comparison)]
+pub fn for_serde() {
+    let per = Person {
+        color_hex: "ffffff/000000".to_string(),
+        fav_num: 6,
+    };
+    let mut st = Storage::new(Json);
+    assert_eq!(st.has_data(), false);
+    st.save(&per).unwrap();
+    assert_eq!(st.load().unwrap(), per)
+}
+
+#[test]
+#[allow(clippy::bool_assert_comparison)]
+pub fn for_wincode() {
+    let per = Person {
+        color_hex: "ffffff/000000".to_string(),
+        fav_num: 6,
+    };
+    let mut st = Storage::new(Wincode);
+    assert_eq!(st.has_data(), false);
+    st.save(&per).unwrap();
+    assert_eq!(st.load().unwrap(), per)
+}
+
+#[cfg(feature = "bincode")]
+#[test]
+pub fn for_bincode() {
+    let per = Person {
+        color_hex: "ffffff/000000".to_string(),
+        fav_num: 6,
+    };
+    let mut st = Storage::new(formats::Bincode);
+    assert!(!st.has_data());
+    st.save(&per).unwrap();
+    assert_eq!(st.load().unwrap(), per);
+    assert_eq!(load_any::<Person>(&st.blob().unwrap().unwrap()).unwrap().1, per);
+
+    let bytes = formats::Bincode.to_bytes(&per).unwrap();
+    let cut: Result<Person, _> = formats::Bincode.deserialize_from(&mut &bytes[..bytes.len() - 1]);
+    assert!(matches!(cut, Err(StorageError::Io(e)) if e.kind() == io::ErrorKind::UnexpectedEof));
+}
+
+#[cfg(feature = "msgpack")]
+#[test]
+pub fn for_msgpack() {
+    let per = Person {
+        color_hex: "ffffff/000000".to_string(),
+        fav_num: 6,
+    };
+    let mut st = Storage::new(formats::MessagePack);
+    assert!(!st.has_data());
+    st.save(&per).unwrap();
+    assert_eq!(st.load().unwrap(), per);
+    assert_eq!(load_any::<Person>(&st.blob().unwrap().unwrap()).unwrap().1, per);
+
+    let mut bytes = formats::MessagePack.to_bytes(&per).unwrap();
+    bytes.push(0);
+    assert!(matches!(
+        Serializer::<Person>::from_bytes(&formats::MessagePack, &bytes),
+        Err(StorageError::Decode { .. })
+    ));
+}
+
+#[cfg(feature = "cbor")]
+#[test]
+pub fn for_cbor() {
+    let per = Person {
+        color_hex: "ffffff/000000".to_string(),
+        fav_num: 6,
+    };
+    let mut st = Storage::new(formats::Cbor);
+    assert!(!st.has_data());
+    st.save(&per).unwrap();
+    assert_eq!(st.load().unwrap(), per);
+    assert_eq!(load_any::<Person>(&st.blob().unwrap().unwrap()).unwrap().1, per);
+
+    let mut bytes = formats::Cbor.to_bytes(&per).unwrap();
+    bytes.push(0);
+    assert!(matches!(
+        Serializer::<Person>::from_bytes(&formats::Cbor, &bytes),
+        Err(StorageError::Decode { .. })
+    ));
+}
+
+#[cfg(feature = "postcard")]
+#[test]
+pub fn for_postcard() {
+    let per = Person {
+        color_hex: "ffffff/000000".to_string(),
+        fav_num: 6,
+    };
+    let mut st = Storage::new(formats::Postcard);
+    assert!(!st.has_data());
+    st.save(&per).unwrap();
+    assert_eq!(st.load().unwrap(), per);
+    assert_eq!(load_any::<Person>(&st.blob().unwrap().unwrap()).unwrap().1, per)
+}
+
+#[test]
+pub fn convert_between_serializers() {
+    let per = Person {
+        color_hex: "ffffff/000000".to_string(),
+        fav_num: 6,
+    };
+    let mut st = Storage::new(Borsh);
+    st.save(&per).unwrap();
+
+    let json = st.convert(Json).unwrap();
+    assert_eq!(json.load().unwrap(), per);
+    let wincode = json.convert(Wincode).unwrap();
+    assert_eq!(wincode.load().unwrap(), per);
+
+    let empty = Storage::<Person, _>::new(Borsh).convert(Json).unwrap();
+    assert!(!empty.has_data());
+}
+
+#[test]
+pub fn convert_rejects_undecodable_payload() {
+    let st = Storage::<Person, _>::with_backend(
+        Borsh,
+        Memory::from(envelope::seal(Format::Borsh, 0, &[0xff, 0x01]).unwrap()),
+    );
+    match st.convert(Json) {
+        Err(StorageError::Decode { serializer, offset, .. }) => {
+            assert_eq!(serializer, "Borsh");
+            assert_eq!(offset, Some(2));
+        }
+        other => panic!("expected decode error, got {:?}", other.map(|s| s.backend)),
+    }
+}
+
+#[test]
+pub fn envelope_detects_serializer_and_version() {
+    let per = Person {
+        color_hex: "ffffff/000000".to_string(),
+        fav_num: 6,
+    };
+    let mut st = Storage::new(Wincode).with_schema_version(2);
+    st.save(&per).unwrap();
+    let blob = st.blob().unwrap().unwrap().to_vec();
+
+    let (header, any) = load_any::<Person>(&blob).unwrap();
+    assert_eq!(header.format, Format::Wincode);
+    assert_eq!(header.schema_version, 2);
+    assert_eq!(any, per);
+
+    let err = Storage::<Person, _>::open(Borsh, blob.clone()).err().unwrap();
+    assert!(matches!(
+        err,
+        StorageError::FormatMismatch {
+            expected: Format::Borsh,
+            found: Format::Wincode
+        }
+    ));
+
+    let reopened = Storage::<Person, _>::open(Wincode, blob).unwrap();
+    assert_eq!(reopened.load().unwrap(), per);
+
+    let older = Storage::<Person, _>::with_backend(Wincode, st.backend().clone())
+        .with_schema_version(1);
+    let err = older.load().unwrap_err();
+    assert!(matches!(
+        err,
+        StorageError::SchemaVersionMismatch {
+            expected: 1,
+            found: 2
+        }
+    ));
+}
+
+#[test]
+pub fn errors_are_typed() {
+    let st = Storage::<Person, _>::new(Json);
+    assert!(matches!(st.load(), Err(StorageError::Empty)));
+
+    let mut st = Storage::new(Borsh);
+    st.save(&Person {
+        color_hex: "ffffff".to_string(),
+        fav_num: 6,
+    })
+    .unwrap();
+    let mut blob = st.blob().unwrap().unwrap().into_owned();
+    *blob.last_mut().unwrap() ^= 0xff;
+    assert!(matches!(
+        Storage::<Person, _>::open(Borsh, blob.clone()),
+        Err(StorageError::ChecksumMismatch { .. })
+    ));
+    blob.truncate(blob.len() - 3);
+    assert!(matches!(
+        Storage::<Person, _>::open(Borsh, blob),
+        Err(StorageError::Truncated { .. })
+    ));
+
+    let json = br#"{"color_hex": "ffffff", "fav_num": "six"}"#;
+    match Json.from_bytes(json) {
+        Err::<Person, _>(StorageError::Decode {
+            serializer, offset, ..
+        }) => {
+            assert_eq!(serializer, "Serde_Json");
+            let six = json.windows(5).position(|w| w == br#""six""#).unwrap();
+            assert!((six..six + 5).contains(&offset.unwrap()));
+        }
+        other => panic!("expected decode error, got {other:?}"),
+    }
+}
+
+/*
+*
+
+
+Bonus Challenges (Optional)
+If you want to extend the challenge:
+1 Add a method to convert between different serializers
+
+*/
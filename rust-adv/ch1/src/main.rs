@@ -1,277 +1,466 @@
-
-#![feature(test)]
-
-extern crate test;
-use std::{ error::Error, fmt::Debug as db, marker::PhantomData};
+//! `ch1` command line: convert, inspect and benchmark storage blobs.
+//!
+//! ```text
+//! ch1 convert --type person --to json person.bin person.json
+//! ch1 inspect --type person person.json
+//! ch1 bench --type person person.bin
+//! ```
+//!
+//! Inputs are either blobs written by `Storage` (their header names the format) or
+//! raw payloads, whose format is given with `--from`; an empty file is a raw payload.
+//! `bench` is a quick in-process timing of one value; `cargo bench -p ch1` runs the
+//! full criterion suite.
+
+use std::{
+    fmt::Debug as db,
+    fs,
+    hint::black_box,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::{Duration, Instant},
+};
 
 use borsh::{BorshDeserialize, BorshSerialize};
-use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use ch1::{
+    Borsh, Json, Person, Serializer, Wincode,
+    envelope::{self, Format},
+    error::StorageError,
+    load_any,
+    schema::Storable,
+};
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::{Serialize, de::DeserializeOwned};
 use wincode::{SchemaRead, SchemaWrite, config::DefaultConfig};
 
-pub trait Serializer<T: db> {
-    fn name(&self) -> &'static str;
-    fn to_bytes(&self, value: &T) -> Result<Vec<u8>, Box<dyn Error>>;
-    fn from_bytes(&self, bytes: &[u8]) -> Result<T, Box<dyn Error>>;
+#[derive(Parser)]
+#[command(
+    version,
+    about = "Convert, inspect and benchmark ch1 storage blobs",
+    after_help = "Formats are those compiled into this build. Pod, Anchor, Compressed and \
+                  Encrypted blobs are not supported."
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
 }
 
-struct Borsh;
-struct Wincode;
-struct Json;
-
-impl<T: db + BorshDeserialize + BorshSerialize> Serializer<T> for Borsh {
-    fn to_bytes(&self, value: &T) -> Result<Vec<u8>, Box<dyn Error>> {
-        borsh::to_vec(value).map_err(|e| e.into())
-    }
-
-    fn from_bytes(&self, bytes: &[u8]) -> Result<T, Box<dyn Error>> {
-        borsh::from_slice(bytes).map_err(|e| e.into())
-    }
+#[derive(Subcommand)]
+enum Command {
+    /// Re-encode a value in another format.
+    Convert {
+        /// Type of the value; only `person` is built in.
+        #[arg(long = "type", value_enum)]
+        ty: TypeName,
+        /// Format of a raw input; blobs name their own.
+        #[arg(long, value_enum)]
+        from: Option<Codec>,
+        /// Output format. Pod, Anchor, Compressed and Encrypted are not supported.
+        #[arg(long, value_enum)]
+        to: Codec,
+        /// Write the bare payload instead of a blob with a header.
+        #[arg(long)]
+        raw: bool,
+        input: PathBuf,
+        output: PathBuf,
+    },
+    /// Print a blob's header and, with `--type`, its value and layout.
+    Inspect {
+        /// Type of the value; only `person` is built in.
+        #[arg(long = "type", value_enum)]
+        ty: Option<TypeName>,
+        #[arg(long, value_enum)]
+        from: Option<Codec>,
+        input: PathBuf,
+    },
+    /// Time encoding and decoding of the input's value with every serializer.
+    Bench {
+        /// Type of the value; only `person` is built in.
+        #[arg(long = "type", value_enum)]
+        ty: TypeName,
+        #[arg(long, value_enum)]
+        from: Option<Codec>,
+        #[arg(long, default_value_t = 10_000)]
+        iters: u32,
+        input: PathBuf,
+    },
+}
 
-    fn name(&self) -> &'static str {
-        "Borsh"
-    }
+/// The types `--type` can name. Only `Person` is built in.
+#[derive(Clone, Copy, ValueEnum)]
+enum TypeName {
+    Person,
 }
 
-impl<T: db + Serialize + DeserializeOwned> Serializer<T> for Json {
-    fn to_bytes(&self, value: &T) -> Result<Vec<u8>, Box<dyn Error>> {
-        serde_json::to_vec(value).map_err(|e| e.into())
-    }
+/// Calls `$f::<T>(..)` with `T` the type named by `--type`.
+macro_rules! with_type {
+    ($ty:expr, $f:ident($($arg:expr),*)) => {
+        match $ty {
+            TypeName::Person => $f::<Person>($($arg),*),
+        }
+    };
+}
 
-    fn from_bytes(&self, bytes: &[u8]) -> Result<T, Box<dyn Error>> {
-        serde_json::from_slice(bytes).map_err(|e| e.into())
-    }
+/// Formats `--from` and `--to` can name: every serde-compatible one in this build.
+#[derive(Clone, Copy, PartialEq, Debug, ValueEnum)]
+enum Codec {
+    Borsh,
+    Wincode,
+    Json,
+    #[cfg(feature = "bincode")]
+    Bincode,
+    #[cfg(feature = "msgpack")]
+    #[value(name = "msgpack")]
+    MessagePack,
+    #[cfg(feature = "cbor")]
+    Cbor,
+    #[cfg(feature = "postcard")]
+    Postcard,
+}
 
-    fn name(&self) -> &'static str {
-        "Serde_Json"
-    }
+/// What a `--type` has to implement; `#[storable]` structs do.
+trait Record:
+    db
+    + Storable
+    + BorshSerialize
+    + BorshDeserialize
+    + Serialize
+    + DeserializeOwned
+    + SchemaWrite<DefaultConfig, Src = Self>
+    + for<'de> SchemaRead<'de, DefaultConfig, Dst = Self>
+{
 }
 
-impl<T: db + SchemaWrite<DefaultConfig, Src = T> + for<'de> SchemaRead<'de, DefaultConfig, Dst = T>>
-    Serializer<T> for Wincode
+impl<T> Record for T where
+    T: db
+        + Storable
+        + BorshSerialize
+        + BorshDeserialize
+        + Serialize
+        + DeserializeOwned
+        + SchemaWrite<DefaultConfig, Src = T>
+        + for<'de> SchemaRead<'de, DefaultConfig, Dst = T>
 {
-    fn to_bytes(&self, value: &T) -> Result<Vec<u8>, Box<dyn Error>> {
-        wincode::serialize(value).map_err(|e| e.into())
+}
+
+impl Codec {
+    fn format(self) -> Format {
+        match self {
+            Codec::Borsh => Format::Borsh,
+            Codec::Wincode => Format::Wincode,
+            Codec::Json => Format::Json,
+            #[cfg(feature = "bincode")]
+            Codec::Bincode => Format::Bincode,
+            #[cfg(feature = "msgpack")]
+            Codec::MessagePack => Format::MessagePack,
+            #[cfg(feature = "cbor")]
+            Codec::Cbor => Format::Cbor,
+            #[cfg(feature = "postcard")]
+            Codec::Postcard => Format::Postcard,
+        }
     }
 
-    fn from_bytes(&self, bytes: &[u8]) -> Result<T, Box<dyn Error>> {
-        wincode::deserialize(bytes).map_err(|e| e.into())
+    fn encode<T: Record>(self, value: &T) -> Result<Vec<u8>, StorageError> {
+        match self {
+            Codec::Borsh => Borsh.to_bytes(value),
+            Codec::Wincode => Wincode.to_bytes(value),
+            Codec::Json => Json.to_bytes(value),
+            #[cfg(feature = "bincode")]
+            Codec::Bincode => ch1::formats::Bincode.to_bytes(value),
+            #[cfg(feature = "msgpack")]
+            Codec::MessagePack => ch1::formats::MessagePack.to_bytes(value),
+            #[cfg(feature = "cbor")]
+            Codec::Cbor => ch1::formats::Cbor.to_bytes(value),
+            #[cfg(feature = "postcard")]
+            Codec::Postcard => ch1::formats::Postcard.to_bytes(value),
+        }
     }
 
-    fn name(&self) -> &'static str {
-        "WinCode"
+    fn decode<T: Record>(self, bytes: &[u8]) -> Result<T, StorageError> {
+        match self {
+            Codec::Borsh => Borsh.from_bytes(bytes),
+            Codec::Wincode => Wincode.from_bytes(bytes),
+            Codec::Json => Json.from_bytes(bytes),
+            #[cfg(feature = "bincode")]
+            Codec::Bincode => ch1::formats::Bincode.from_bytes(bytes),
+            #[cfg(feature = "msgpack")]
+            Codec::MessagePack => ch1::formats::MessagePack.from_bytes(bytes),
+            #[cfg(feature = "cbor")]
+            Codec::Cbor => ch1::formats::Cbor.from_bytes(bytes),
+            #[cfg(feature = "postcard")]
+            Codec::Postcard => ch1::formats::Postcard.from_bytes(bytes),
+        }
     }
 }
 
-pub struct Storage<T, S> {
-    data: Option<Vec<u8>>,
-    serializer: S,
-    _type: PhantomData<T>,
+/// A decoded input and the schema version it carried (0 for raw payloads).
+struct Decoded<T> {
+    format: Format,
+    schema_version: u16,
+    value: T,
 }
 
-impl<T: db, S: Serializer<T>> Storage<T, S> {
-    pub fn new(serializer: S) -> Self {
-        Storage {
-            data: None,
-            serializer,
-            _type: PhantomData,
-        }
-    }
-    pub fn save(&mut self, value: &T) -> Result<(), Box<dyn Error>> {
-        let bytes = self.serializer.to_bytes(value)?;
-        println!("to_bytes {:?} : \n  {:?}", self.serializer.name(), bytes);
-        self.data = Some(bytes);
-        Ok(())
-    }
-    pub fn load(&self) -> Result<T, Box<dyn Error>> {
-        match &self.data {
-            Some(data) => {
-                let obj = self.serializer.from_bytes(data);
-                println!("From_bytes {:?} : \n  {:?}", self.serializer.name(), obj);
-                obj
+fn decode<T: Record>(bytes: &[u8], from: Option<Codec>) -> Result<Decoded<T>, StorageError> {
+    match envelope::header(bytes) {
+        Ok(header) => {
+            if let Some(from) = from.filter(|from| from.format() != header.format) {
+                return Err(StorageError::FormatMismatch {
+                    expected: from.format(),
+                    found: header.format,
+                });
             }
-            None => Err("no data".into()),
+            let (header, value) = load_any(bytes)?;
+            Ok(Decoded {
+                format: header.format,
+                schema_version: header.schema_version,
+                value,
+            })
         }
-
-        //self.serializer.from_bytes(self.data)?;
-    }
-    pub fn has_data(&self) -> bool {
-        self.data.is_some()
+        Err(StorageError::BadMagic | StorageError::Truncated { actual: 0, .. }) => {
+            let from = from.ok_or_else(|| {
+                StorageError::Unsupported("raw input without --from".to_string())
+            })?;
+            Ok(Decoded {
+                format: from.format(),
+                schema_version: 0,
+                value: from.decode(bytes)?,
+            })
+        }
+        Err(e) => Err(e),
     }
 }
 
-//5 pending
-#[derive(
-    BorshSerialize,
-    BorshDeserialize,
-    Serialize,
-    Deserialize,
-    PartialEq,
-    Debug,
-    SchemaWrite,
-    SchemaRead,
-)]
-struct Person {
-    pub color_hex: String,
-    pub fav_num: u64,
+fn convert<T: Record>(
+    input: &Path,
+    from: Option<Codec>,
+    to: Codec,
+    raw: bool,
+    output: &Path,
+    out: &mut impl Write,
+) -> Result<(), StorageError> {
+    let bytes = fs::read(input)?;
+    let decoded = decode::<T>(&bytes, from)?;
+    let mut encoded = to.encode(&decoded.value)?;
+    if !raw {
+        encoded = envelope::seal(to.format(), decoded.schema_version, &encoded)?;
+    }
+    fs::write(output, &encoded)?;
+    writeln!(
+        out,
+        "{:?} ({} bytes) -> {:?} ({} bytes)",
+        decoded.format,
+        bytes.len(),
+        to.format(),
+        encoded.len()
+    )?;
+    Ok(())
 }
 
-//\/\/\/\/\/\/\/\Test/\/\/\/\/\/\/\
-
-#[test]
-pub fn for_borsh() {
-    let per = Person {
-        color_hex: "ffffff/000000".to_string(),
-        fav_num: 6,
+fn inspect_header(bytes: &[u8], out: &mut impl Write) -> Result<(), StorageError> {
+    let header = match envelope::header(bytes) {
+        Ok(header) => header,
+        Err(StorageError::BadMagic | StorageError::Truncated { actual: 0, .. }) => {
+            writeln!(out, "raw payload, {} bytes", bytes.len())?;
+            return Ok(());
+        }
+        Err(e) => return Err(e),
     };
-    let mut st = Storage::new(Borsh);
-    assert_eq!(st.has_data(), false);
-    st.save(&per).unwrap();
-    assert_eq!(st.load().unwrap(), per)
-}
-
-#[test]
-pub fn for_serde() {
-    let per = Person {
-        color_hex: "ffffff/000000".to_string(),
-        fav_num: 6,
+    writeln!(out, "format          {:?}", header.format)?;
+    writeln!(out, "schema version  {}", header.schema_version)?;
+    writeln!(out, "payload         {} bytes", header.len)?;
+    let checksum = match envelope::open(bytes) {
+        Ok(_) => "ok".to_string(),
+        Err(e) => e.to_string(),
     };
-    let mut st = Storage::new(Json);
-    assert_eq!(st.has_data(), false);
-    st.save(&per).unwrap();
-    assert_eq!(st.load().unwrap(), per)
+    writeln!(out, "checksum        {:08x} ({checksum})", header.checksum)?;
+    Ok(())
 }
 
-#[test]
-pub fn for_wincode() {
-    let per = Person {
-        color_hex: "ffffff/000000".to_string(),
-        fav_num: 6,
-    };
-    let mut st = Storage::new(Wincode);
-    assert_eq!(st.has_data(), false);
-    st.save(&per).unwrap();
-    assert_eq!(st.load().unwrap(), per)
+fn inspect_value<T: Record>(bytes: &[u8], from: Option<Codec>, out: &mut impl Write) -> Result<(), StorageError> {
+    let decoded = decode::<T>(bytes, from)?;
+    writeln!(out, "\n{:#?}\n\n{}", decoded.value, T::schema())?;
+    Ok(())
 }
 
-
-//\/\/\/\//\\/\\\/\\/ bench \/\/\/\/\/\/\
-
-#[bench]
-fn bench_borsh_serialize(b: &mut test::Bencher) {
-    let per = Person {
-        color_hex: "ffffff".to_string(),
-        fav_num: 702496809348,
-    };
-    
-    b.iter(|| {            
-        let mut storage = Storage::new(Borsh);
-        storage.save(&per).unwrap();
-
-    });
+fn time(iters: u32, mut f: impl FnMut() -> Result<(), StorageError>) -> Result<Duration, StorageError> {
+    let start = Instant::now();
+    for _ in 0..iters {
+        f()?;
+    }
+    Ok(start.elapsed() / iters.max(1))
 }
 
-
-
-#[bench]
-fn bench_json_serialize(b: &mut test::Bencher) {
-    let per = Person {
-        color_hex: "ffffff".to_string(),
-        fav_num: 702496809348,
-    };
-    
-    b.iter(|| {
-
-        let mut storage = Storage::new(Json);
-        storage.save(&per).unwrap();
-
-    });
-    
-            
-       
+fn bench_one<T: Record, S: Serializer<T>>(
+    serializer: S,
+    value: &T,
+    iters: u32,
+    out: &mut impl Write,
+) -> Result<(), StorageError> {
+    let bytes = serializer.to_bytes(value)?;
+    let encode = time(iters, || serializer.to_bytes(black_box(value)).map(drop))?;
+    let decode = time(iters, || serializer.from_bytes(black_box(&bytes)).map(drop))?;
+    writeln!(
+        out,
+        "{:<12} {:>10} {:>14?} {:>14?}",
+        serializer.name(),
+        bytes.len(),
+        encode,
+        decode
+    )?;
+    Ok(())
 }
 
-#[bench]
-fn bench_wincode_serialize(b: &mut test::Bencher) {
-    let per = Person {
-        color_hex: "ffffff".to_string(),
-        fav_num: 702496809348,
-    };
-    
-    b.iter(|| {
-            
-        let mut storage = Storage::new(Wincode);
-        storage.save(&per).unwrap();
-
-    });
-    
-            
-       
+fn bench<T: Record>(input: &Path, from: Option<Codec>, iters: u32, out: &mut impl Write) -> Result<(), StorageError> {
+    let value = decode::<T>(&fs::read(input)?, from)?.value;
+    writeln!(out, "{:<12} {:>10} {:>14} {:>14}", "serializer", "bytes", "encode", "decode")?;
+    bench_one(Borsh, &value, iters, out)?;
+    bench_one(Wincode, &value, iters, out)?;
+    bench_one(Json, &value, iters, out)?;
+    #[cfg(feature = "bincode")]
+    bench_one(ch1::formats::Bincode, &value, iters, out)?;
+    #[cfg(feature = "msgpack")]
+    bench_one(ch1::formats::MessagePack, &value, iters, out)?;
+    #[cfg(feature = "cbor")]
+    bench_one(ch1::formats::Cbor, &value, iters, out)?;
+    #[cfg(feature = "postcard")]
+    bench_one(ch1::formats::Postcard, &value, iters, out)?;
+    Ok(())
 }
 
-
-#[bench]
-fn bench_borsh_deserialize(b: &mut test::Bencher) {
-    let per = Person {
-        color_hex: "ffffff".to_string(),
-        fav_num: 702496809348,
-    };
-    
-    let mut storage = Storage::new(Borsh);
-    storage.save(&per).unwrap();
-    
-    b.iter(|| {
-
-        storage.load().unwrap();
-    })
+fn run(command: Command, out: &mut impl Write) -> Result<(), StorageError> {
+    match command {
+        Command::Convert {
+            ty,
+            from,
+            to,
+            raw,
+            input,
+            output,
+        } => with_type!(ty, convert(&input, from, to, raw, &output, out)),
+        Command::Inspect { ty, from, input } => {
+            let bytes = fs::read(&input)?;
+            inspect_header(&bytes, out)?;
+            match ty {
+                Some(ty) => with_type!(ty, inspect_value(&bytes, from, out)),
+                None => Ok(()),
+            }
+        }
+        Command::Bench { ty, from, iters, input } => with_type!(ty, bench(&input, from, iters, out)),
+    }
 }
 
-#[bench]
-fn bench_json_deserialize(b: &mut test::Bencher) {
-    let per = Person {
-        color_hex: "ffffff".to_string(),
-        fav_num: 702496809348,
-    };
-    
-    let mut storage = Storage::new(Json);
-    storage.save(&per).unwrap();
-    
-    b.iter(|| {
-
-        storage.load().unwrap();
-    })
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli.command, &mut io::stdout().lock()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
 }
 
-#[bench]
-fn bench_wincode_deserialize(b: &mut test::Bencher) {
-    let per = Person {
-        color_hex: "ffffff".to_string(),
-        fav_num: 702496809348,
-    };
-    
-    let mut storage = Storage::new(Wincode);
-    storage.save(&per).unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ch1::Storage;
 
-    
+    fn person() -> Person {
+        Person {
+            color_hex: "ffffff/000000".to_string(),
+            fav_num: 6,
+        }
+    }
 
-    b.iter(|| {            
-        storage.load().unwrap();
+    fn cli(args: &[&str]) -> Result<String, StorageError> {
+        let cli = Cli::try_parse_from(["ch1"].iter().chain(args)).unwrap();
+        let mut out = Vec::new();
+        run(cli.command, &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
 
-    })
-}
+    #[test]
+    fn converts_blobs_and_raw_payloads() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name).to_str().unwrap().to_string();
+        let mut st = Storage::new(Borsh).with_schema_version(3);
+        st.save(&person()).unwrap();
+        fs::write(path("in.bin"), st.blob().unwrap().unwrap()).unwrap();
+
+        cli(&["convert", "--type", "person", "--to", "json", "--raw", &path("in.bin"), &path("p.json")]).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&fs::read(path("p.json")).unwrap()).unwrap();
+        assert_eq!(json["fav_num"], 6);
+
+        let err = cli(&["convert", "--type", "person", "--to", "wincode", &path("p.json"), &path("p.win")]);
+        assert!(matches!(err, Err(StorageError::Unsupported(_))));
+        cli(&["convert", "--type", "person", "--from", "json", "--to", "wincode", &path("p.json"), &path("p.win")])
+            .unwrap();
+        let (header, decoded) = load_any::<Person>(&fs::read(path("p.win")).unwrap()).unwrap();
+        assert_eq!((header.format, header.schema_version), (Format::Wincode, 0));
+        assert_eq!(decoded, person());
+
+        // Blobs keep their schema version through a conversion.
+        cli(&["convert", "--type", "person", "--to", "wincode", &path("in.bin"), &path("v3.win")]).unwrap();
+        let (header, _) = load_any::<Person>(&fs::read(path("v3.win")).unwrap()).unwrap();
+        assert_eq!(header.schema_version, 3);
+
+        let err = cli(&["convert", "--type", "person", "--from", "json", "--to", "borsh", &path("in.bin"), &path("x")]);
+        assert!(matches!(err, Err(StorageError::FormatMismatch { .. })));
+    }
 
-/*
-*
+    #[test]
+    fn inspects_header_value_and_layout() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("p.bin");
+        let mut st = Storage::new(Wincode);
+        st.save(&person()).unwrap();
+        let mut blob = st.blob().unwrap().unwrap().to_vec();
+        fs::write(&file, &blob).unwrap();
+
+        let text = cli(&["inspect", "--type", "person", file.to_str().unwrap()]).unwrap();
+        assert!(text.starts_with("format          Wincode\nschema version  0\n"));
+        assert!(text.contains("(ok)"));
+        assert!(text.contains("fav_num: 6"));
+        assert!(text.contains("Person (variable size)"));
+
+        *blob.last_mut().unwrap() ^= 1;
+        fs::write(&file, &blob).unwrap();
+        let text = cli(&["inspect", file.to_str().unwrap()]).unwrap();
+        assert!(text.contains("checksum mismatch"), "{text}");
+    }
 
+    #[test]
+    fn empty_input_is_a_raw_payload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name).to_str().unwrap().to_string();
+        fs::write(path("empty"), b"").unwrap();
+
+        assert_eq!(cli(&["inspect", &path("empty")]).unwrap(), "raw payload, 0 bytes\n");
+        let err = cli(&["convert", "--type", "person", "--to", "json", &path("empty"), &path("out")]);
+        assert!(matches!(err, Err(StorageError::Unsupported(_))));
+        let err = cli(&["convert", "--type", "person", "--from", "json", "--to", "borsh", &path("empty"), &path("out")]);
+        assert!(matches!(err, Err(StorageError::Decode { .. })));
+    }
 
-Bonus Challenges (Optional)
-If you want to extend the challenge:
-1 Add a method to convert between different serializers
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn converts_to_optional_formats() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name).to_str().unwrap().to_string();
+        fs::write(path("p.json"), Json.to_bytes(&person()).unwrap()).unwrap();
 
-*/
+        cli(&["convert", "--type", "person", "--from", "json", "--to", "cbor", &path("p.json"), &path("p.cbor")]).unwrap();
+        let (header, decoded) = load_any::<Person>(&fs::read(path("p.cbor")).unwrap()).unwrap();
+        assert_eq!((header.format, decoded), (Format::Cbor, person()));
+    }
 
-fn main() {
-    println!("Serialize and deserialize!");
-    // for_borsh();
-    // for_serde();
-    // for_wincode();
+    #[test]
+    fn bench_reports_every_serializer() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("p.json");
+        fs::write(&file, Json.to_bytes(&person()).unwrap()).unwrap();
+        let text = cli(&["bench", "--type", "person", "--from", "json", "--iters", "10", file.to_str().unwrap()]).unwrap();
+        let names: Vec<_> = text.lines().skip(1).filter_map(|l| l.split_whitespace().next()).collect();
+        assert_eq!(&names[..3], ["Borsh", "WinCode", "Serde_Json"]);
+    }
 }
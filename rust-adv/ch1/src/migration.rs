@@ -0,0 +1,167 @@
+//! Upgrades payloads written by an older schema version of `T` to the current one.
+
+use std::{borrow::Cow, collections::BTreeMap, fmt::Debug as db};
+
+use crate::{Serializer, error::StorageError};
+
+type Step<S> = Box<dyn Fn(&S, &[u8]) -> Result<Vec<u8>, StorageError> + Send + Sync>;
+
+/// Chain of `vN -> vN+1` upgrade functions, keyed by the version they upgrade from.
+pub struct Migrations<S> {
+    steps: BTreeMap<u16, Step<S>>,
+}
+
+impl<S> Default for Migrations<S> {
+    fn default() -> Self {
+        Migrations {
+            steps: BTreeMap::new(),
+        }
+    }
+}
+
+impl<S> Migrations<S> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the upgrade from schema version `from` (stored as `Old`) to `from + 1` (stored as `New`).
+    pub fn register<Old, New>(mut self, from: u16, upgrade: impl Fn(Old) -> New + Send + Sync + 'static) -> Self
+    where
+        Old: db,
+        New: db,
+        S: Serializer<Old> + Serializer<New>,
+    {
+        self.steps.insert(
+            from,
+            Box::new(move |serializer: &S, bytes: &[u8]| {
+                let old: Old = serializer.from_bytes(bytes)?;
+                serializer.to_bytes(&upgrade(old))
+            }),
+        );
+        self
+    }
+
+    /// Runs every step needed to bring `payload` from version `from` to version `to`.
+    pub fn upgrade<'a>(
+        &self,
+        serializer: &S,
+        from: u16,
+        to: u16,
+        payload: &'a [u8],
+    ) -> Result<Cow<'a, [u8]>, StorageError> {
+        let mut bytes = Cow::Borrowed(payload);
+        for version in from..to {
+            let step = self
+                .steps
+                .get(&version)
+                .ok_or(StorageError::MissingMigration { from: version })?;
+            bytes = Cow::Owned(
+                step(serializer, &bytes).map_err(|source| StorageError::Migration {
+                    from: version,
+                    source: Box::new(source),
+                })?,
+            );
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Borsh, Storage, Wincode};
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use wincode::{SchemaRead, SchemaWrite};
+
+    #[derive(BorshSerialize, BorshDeserialize, SchemaWrite, SchemaRead, PartialEq, Debug)]
+    struct PersonV1 {
+        color_hex: String,
+    }
+
+    #[derive(BorshSerialize, BorshDeserialize, SchemaWrite, SchemaRead, PartialEq, Debug)]
+    struct PersonV2 {
+        color_hex: String,
+        fav_num: u64,
+    }
+
+    #[derive(BorshSerialize, BorshDeserialize, SchemaWrite, SchemaRead, PartialEq, Debug)]
+    struct PersonV3 {
+        color_hex: String,
+        fav_num: u64,
+        nickname: Option<String>,
+    }
+
+    fn migrations<S>() -> Migrations<S>
+    where
+        S: Serializer<PersonV1> + Serializer<PersonV2> + Serializer<PersonV3>,
+    {
+        Migrations::new()
+            .register(1, |v1: PersonV1| PersonV2 {
+                color_hex: v1.color_hex,
+                fav_num: 0,
+            })
+            .register(2, |v2: PersonV2| PersonV3 {
+                color_hex: v2.color_hex,
+                fav_num: v2.fav_num,
+                nickname: None,
+            })
+    }
+
+    #[test]
+    fn upgrades_old_borsh_blobs() {
+        let mut v1 = Storage::new(Borsh).with_schema_version(1);
+        v1.save(&PersonV1 {
+            color_hex: "ffffff".to_string(),
+        })
+        .unwrap();
+
+        let v3 = Storage::<PersonV3, _>::open(Borsh, v1.blob().unwrap().unwrap().to_vec())
+            .unwrap()
+            .with_schema_version(3)
+            .with_migrations(migrations());
+        assert_eq!(
+            v3.load().unwrap(),
+            PersonV3 {
+                color_hex: "ffffff".to_string(),
+                fav_num: 0,
+                nickname: None,
+            }
+        );
+    }
+
+    #[test]
+    fn upgrades_old_wincode_blobs_from_middle_version() {
+        let mut v2 = Storage::new(Wincode).with_schema_version(2);
+        v2.save(&PersonV2 {
+            color_hex: "000000".to_string(),
+            fav_num: 7,
+        })
+        .unwrap();
+
+        let v3 = Storage::<PersonV3, _>::open(Wincode, v2.blob().unwrap().unwrap().to_vec())
+            .unwrap()
+            .with_schema_version(3)
+            .with_migrations(migrations());
+        assert_eq!(v3.load().unwrap().fav_num, 7);
+    }
+
+    #[test]
+    fn missing_step_is_reported() {
+        let mut v1 = Storage::new(Borsh).with_schema_version(1);
+        v1.save(&PersonV1 {
+            color_hex: "ffffff".to_string(),
+        })
+        .unwrap();
+
+        let v3 = Storage::<PersonV3, _>::open(Borsh, v1.blob().unwrap().unwrap().to_vec())
+            .unwrap()
+            .with_schema_version(3)
+            .with_migrations(Migrations::new().register(2, |v2: PersonV2| PersonV3 {
+                color_hex: v2.color_hex,
+                fav_num: v2.fav_num,
+                nickname: None,
+            }));
+        let err = v3.load().unwrap_err();
+        assert!(matches!(err, StorageError::MissingMigration { from: 1 }));
+    }
+}
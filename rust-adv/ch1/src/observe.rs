@@ -0,0 +1,200 @@
+//! Hooks for watching what `Storage` does, without printing anything by default.
+//!
+//! Attach an [`Observer`] with `Storage::with_observer` to receive one [`Event`]
+//! per `save` / `load`. [`Metrics`] keeps running totals for long-lived processes;
+//! with the `tracing` feature, [`Tracing`] forwards events to the `tracing` crate.
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use crate::error::StorageError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Save,
+    Load,
+}
+
+/// One finished `save` or `load`.
+#[derive(Debug)]
+pub struct Event<'a> {
+    pub op: Operation,
+    pub serializer: &'static str,
+    /// Encoded payload size, excluding the envelope header. Zero on failure.
+    pub bytes: usize,
+    /// Time spent encoding and writing, or reading and decoding.
+    pub duration: Duration,
+    pub error: Option<&'a StorageError>,
+}
+
+pub trait Observer: Send + Sync {
+    fn observe(&self, event: &Event<'_>);
+}
+
+impl<F: Fn(&Event<'_>) + Send + Sync> Observer for F {
+    fn observe(&self, event: &Event<'_>) {
+        self(event)
+    }
+}
+
+/// Lets the caller keep a handle to an observer it hands to `Storage`, e.g. `Arc<Metrics>`.
+impl<O: Observer + ?Sized> Observer for Arc<O> {
+    fn observe(&self, event: &Event<'_>) {
+        (**self).observe(event)
+    }
+}
+
+/// Running totals per operation, cheap enough to leave attached.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    save: Counters,
+    load: Counters,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    calls: AtomicU64,
+    errors: AtomicU64,
+    bytes: AtomicU64,
+    nanos: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Totals {
+    pub calls: u64,
+    pub errors: u64,
+    pub bytes: u64,
+    pub duration: Duration,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn totals(&self, op: Operation) -> Totals {
+        let c = self.counters(op);
+        Totals {
+            calls: c.calls.load(Ordering::Relaxed),
+            errors: c.errors.load(Ordering::Relaxed),
+            bytes: c.bytes.load(Ordering::Relaxed),
+            duration: Duration::from_nanos(c.nanos.load(Ordering::Relaxed)),
+        }
+    }
+
+    fn counters(&self, op: Operation) -> &Counters {
+        match op {
+            Operation::Save => &self.save,
+            Operation::Load => &self.load,
+        }
+    }
+}
+
+impl Observer for Metrics {
+    fn observe(&self, event: &Event<'_>) {
+        let c = self.counters(event.op);
+        c.calls.fetch_add(1, Ordering::Relaxed);
+        if event.error.is_some() {
+            c.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        c.bytes.fetch_add(event.bytes as u64, Ordering::Relaxed);
+        let nanos = u64::try_from(event.duration.as_nanos()).unwrap_or(u64::MAX);
+        c.nanos.fetch_add(nanos, Ordering::Relaxed);
+    }
+}
+
+/// Emits a `debug` event per call and a `warn` event per failure under the `ch1::storage` target.
+#[cfg(feature = "tracing")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Tracing;
+
+#[cfg(feature = "tracing")]
+impl Observer for Tracing {
+    fn observe(&self, event: &Event<'_>) {
+        let duration_us = event.duration.as_micros() as u64;
+        match event.error {
+            Some(error) => tracing::warn!(
+                target: "ch1::storage",
+                op = ?event.op,
+                serializer = event.serializer,
+                duration_us,
+                %error,
+            ),
+            None => tracing::debug!(
+                target: "ch1::storage",
+                op = ?event.op,
+                serializer = event.serializer,
+                bytes = event.bytes,
+                duration_us,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Borsh, Json, Person, Serializer, Storage};
+    use std::sync::Mutex;
+
+    fn person() -> Person {
+        Person {
+            color_hex: "ffffff".to_string(),
+            fav_num: 6,
+        }
+    }
+
+    #[test]
+    fn reports_saves_loads_and_errors() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let mut st = Storage::new(Borsh).with_observer(move |e: &Event<'_>| {
+            sink.lock()
+                .unwrap()
+                .push((e.op, e.serializer, e.bytes, e.error.is_some()));
+        });
+        assert!(st.load().is_err());
+        st.save(&person()).unwrap();
+        st.load().unwrap();
+
+        let payload = Borsh.to_bytes(&person()).unwrap().len();
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                (Operation::Load, "Borsh", 0, true),
+                (Operation::Save, "Borsh", payload, false),
+                (Operation::Load, "Borsh", payload, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn metrics_accumulate() {
+        let metrics = Arc::new(Metrics::new());
+        let mut st = Storage::new(Json).with_observer(metrics.clone());
+        for _ in 0..3 {
+            st.save(&person()).unwrap();
+        }
+        st.load().unwrap();
+
+        let saves = metrics.totals(Operation::Save);
+        assert_eq!(saves.calls, 3);
+        assert_eq!(saves.errors, 0);
+        assert_eq!(saves.bytes, 3 * Json.to_bytes(&person()).unwrap().len() as u64);
+        assert_eq!(metrics.totals(Operation::Load).calls, 1);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn tracing_observer_without_subscriber() {
+        let mut st = Storage::new(Borsh).with_observer(Tracing);
+        assert!(st.load().is_err());
+        st.save(&person()).unwrap();
+        assert_eq!(st.load().unwrap(), person());
+    }
+}
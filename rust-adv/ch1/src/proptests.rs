@@ -0,0 +1,163 @@
+//! Property tests over arbitrary values and arbitrary bytes, for every serializer.
+//!
+//! Round trips must be lossless, conversions between formats must preserve the
+//! value, and no decoder may panic on untrusted input.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use bytemuck::{Pod as PodType, Zeroable};
+use proptest::{collection::vec, prelude::*, sample::Index};
+use serde::{Deserialize, Serialize};
+use wincode::{SchemaRead, SchemaWrite};
+
+use crate::{
+    Borsh, Json, Person, Serializer, Storage, Wincode, convert, load_any, stream::FrameReader,
+    zero_copy::Pod,
+};
+
+#[derive(
+    BorshSerialize, BorshDeserialize, Serialize, Deserialize, SchemaWrite, SchemaRead, PartialEq, Debug,
+)]
+struct Sample {
+    id: u64,
+    delta: i64,
+    flags: u16,
+    active: bool,
+    name: String,
+    data: Vec<u8>,
+    limit: Option<i32>,
+    people: Vec<Person>,
+}
+
+#[cfg(feature = "anchor")]
+impl crate::anchor::AnchorAccount for Sample {
+    const NAME: &'static str = "Sample";
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Debug, PodType, Zeroable)]
+struct Tick {
+    slot: u64,
+    price: i64,
+}
+
+fn person() -> impl Strategy<Value = Person> {
+    (any::<String>(), any::<u64>()).prop_map(|(color_hex, fav_num)| Person { color_hex, fav_num })
+}
+
+fn sample() -> impl Strategy<Value = Sample> {
+    (
+        (any::<u64>(), any::<i64>(), any::<u16>(), any::<bool>()),
+        any::<String>(),
+        vec(any::<u8>(), 0..256),
+        any::<Option<i32>>(),
+        vec(person(), 0..4),
+    )
+        .prop_map(|((id, delta, flags, active), name, data, limit, people)| Sample {
+            id,
+            delta,
+            flags,
+            active,
+            name,
+            data,
+            limit,
+            people,
+        })
+}
+
+fn round_trip<S: Serializer<Sample>>(serializer: S, value: &Sample) {
+    let bytes = serializer.to_bytes(value).unwrap();
+    assert_eq!(&serializer.from_bytes(&bytes).unwrap(), value, "{}", serializer.name());
+
+    let mut st = Storage::new(serializer);
+    st.save(value).unwrap();
+    assert_eq!(&st.load().unwrap(), value);
+}
+
+/// Flips bytes of a valid encoding and cuts it short; decoding may fail but must not panic.
+fn mutate<S: Serializer<Sample>>(serializer: S, value: &Sample, edits: &[(Index, u8)], cut: Index) {
+    let mut bytes = serializer.to_bytes(value).unwrap();
+    let _ = serializer.from_bytes(&bytes[..cut.index(bytes.len() + 1)]);
+    for (at, mask) in edits {
+        let i = at.index(bytes.len());
+        bytes[i] ^= mask;
+    }
+    let _ = serializer.from_bytes(&bytes);
+}
+
+/// Runs `$f` against every serializer compiled into the crate.
+macro_rules! for_each_serializer {
+    ($f:ident($($arg:expr),*)) => {{
+        $f(Borsh, $($arg),*);
+        $f(Wincode, $($arg),*);
+        $f(Json, $($arg),*);
+        #[cfg(feature = "bincode")]
+        $f(crate::formats::Bincode, $($arg),*);
+        #[cfg(feature = "msgpack")]
+        $f(crate::formats::MessagePack, $($arg),*);
+        #[cfg(feature = "cbor")]
+        $f(crate::formats::Cbor, $($arg),*);
+        #[cfg(feature = "postcard")]
+        $f(crate::formats::Postcard, $($arg),*);
+        #[cfg(feature = "zstd")]
+        $f(crate::compress::Compressed::new(Json, crate::compress::Zstd::default()).with_threshold(0), $($arg),*);
+        #[cfg(feature = "lz4")]
+        $f(crate::compress::Compressed::new(Borsh, crate::compress::Lz4).with_threshold(0), $($arg),*);
+        #[cfg(feature = "deflate")]
+        $f(crate::compress::Compressed::new(Wincode, crate::compress::Deflate::default()).with_threshold(0), $($arg),*);
+        #[cfg(feature = "anchor")]
+        $f(crate::anchor::Anchor, $($arg),*);
+        #[cfg(feature = "encryption")]
+        $f(crate::encrypt::Encrypted::<_, crate::encrypt::ChaCha20Poly1305>::new(Borsh, &[7u8; 32].into()), $($arg),*);
+    }};
+}
+
+fn decode<S: Serializer<Sample>>(serializer: S, bytes: &[u8]) {
+    let _ = serializer.from_bytes(bytes);
+    let _ = serializer.deserialize_from(&mut &bytes[..]);
+}
+
+proptest! {
+    #[test]
+    fn every_serializer_round_trips(value in sample()) {
+        for_each_serializer!(round_trip(&value));
+    }
+
+    #[test]
+    fn pod_round_trips(slot in any::<u64>(), price in any::<i64>()) {
+        let tick = Tick { slot, price };
+        let bytes = Pod.to_bytes(&tick).unwrap();
+        prop_assert_eq!(Serializer::<Tick>::from_bytes(&Pod, &bytes).unwrap(), tick);
+    }
+
+    #[test]
+    fn borsh_json_wincode_conversion_preserves_value(value in sample()) {
+        let borsh = Borsh.to_bytes(&value).unwrap();
+        let json = convert::<Sample, _, _>(&Borsh, &Json, &borsh).unwrap();
+        let wincode = convert::<Sample, _, _>(&Json, &Wincode, &json).unwrap();
+        let decoded: Sample = Wincode.from_bytes(&wincode).unwrap();
+        prop_assert_eq!(&decoded, &value);
+
+        let mut st = Storage::new(Borsh);
+        st.save(&value).unwrap();
+        let converted = st.convert(Json).unwrap().convert(Wincode).unwrap();
+        prop_assert_eq!(&converted.load().unwrap(), &value);
+        prop_assert_eq!(load_any::<Sample>(&converted.blob().unwrap().unwrap()).unwrap().1, value);
+    }
+
+    #[test]
+    fn random_bytes_never_panic(bytes in vec(any::<u8>(), 0..512)) {
+        for_each_serializer!(decode(&bytes));
+        let _ = Serializer::<Tick>::from_bytes(&Pod, &bytes);
+        let _ = load_any::<Sample>(&bytes);
+        let _ = FrameReader::<_, _, Sample>::new(bytes.as_slice(), Borsh).count();
+    }
+
+    #[test]
+    fn corrupted_encodings_never_panic(
+        value in sample(),
+        edits in vec((any::<Index>(), 1..=u8::MAX), 1..8),
+        cut in any::<Index>(),
+    ) {
+        for_each_serializer!(mutate(&value, &edits, cut));
+    }
+}
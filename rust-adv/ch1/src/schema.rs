@@ -0,0 +1,265 @@
+//! Layout descriptions of `#[storable]` structs: field names, types and Borsh offsets.
+//!
+//! Offsets are known up to the first variable-size field (`String`, `Vec`, `Option`, ...).
+//! Compare a [`Schema`] against an on-chain account layout with [`Schema::diff`].
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt,
+};
+
+pub use ch1_derive::storable;
+
+/// Borsh encoded size of the type, when it does not depend on the value.
+pub trait BorshSize {
+    const FIXED_SIZE: Option<usize>;
+}
+
+macro_rules! fixed_size {
+    ($($t:ty),*) => {$(
+        impl BorshSize for $t {
+            const FIXED_SIZE: Option<usize> = Some(size_of::<$t>());
+        }
+    )*};
+}
+
+macro_rules! variable_size {
+    ($($t:ty => [$($g:ident),*]),*) => {$(
+        impl<$($g),*> BorshSize for $t {
+            const FIXED_SIZE: Option<usize> = None;
+        }
+    )*};
+}
+
+fixed_size!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64, bool, ());
+variable_size!(
+    String => [],
+    Vec<T> => [T],
+    Option<T> => [T],
+    BTreeMap<K, V> => [K, V],
+    BTreeSet<T> => [T],
+    HashMap<K, V, S> => [K, V, S],
+    HashSet<T, S> => [T, S]
+);
+
+impl<T: BorshSize, const N: usize> BorshSize for [T; N] {
+    const FIXED_SIZE: Option<usize> = match T::FIXED_SIZE {
+        Some(size) => Some(size * N),
+        None => None,
+    };
+}
+
+impl<T: BorshSize> BorshSize for Box<T> {
+    const FIXED_SIZE: Option<usize> = T::FIXED_SIZE;
+}
+
+/// Total of `sizes`, or `None` if any of them is variable.
+pub const fn sum(sizes: &[Option<usize>]) -> Option<usize> {
+    let mut total = 0;
+    let mut i = 0;
+    while i < sizes.len() {
+        match sizes[i] {
+            Some(size) => total += size,
+            None => return None,
+        }
+        i += 1;
+    }
+    Some(total)
+}
+
+/// Implemented by `#[storable]`.
+pub trait Storable {
+    fn schema() -> Schema;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub name: &'static str,
+    pub ty: &'static str,
+    /// Borsh offset from the start of the struct, if every field before it is fixed-size.
+    pub offset: Option<usize>,
+    pub size: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schema {
+    pub name: &'static str,
+    pub fields: Vec<Field>,
+    pub fixed_size: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Added(Field),
+    Removed(Field),
+    /// Same name, different type, offset or size.
+    Changed { before: Field, after: Field },
+}
+
+impl Schema {
+    /// Builds a schema from `(name, type, fixed size)` per field, in declaration order.
+    pub fn new(name: &'static str, fields: &[(&'static str, &'static str, Option<usize>)]) -> Self {
+        let mut offset = Some(0);
+        let fields = fields
+            .iter()
+            .map(|&(name, ty, size)| {
+                let field = Field {
+                    name,
+                    ty,
+                    offset,
+                    size,
+                };
+                offset = offset.zip(size).map(|(offset, size)| offset + size);
+                field
+            })
+            .collect();
+        Schema {
+            name,
+            fields,
+            fixed_size: offset,
+        }
+    }
+
+    pub fn field(&self, name: &str) -> Option<&Field> {
+        self.fields.iter().find(|f| f.name == name)
+    }
+
+    /// Field-by-field differences from `self` to `other`, matched by name.
+    pub fn diff(&self, other: &Schema) -> Vec<Change> {
+        let mut changes = Vec::new();
+        for before in &self.fields {
+            match other.field(before.name) {
+                None => changes.push(Change::Removed(before.clone())),
+                Some(after) if after != before => changes.push(Change::Changed {
+                    before: before.clone(),
+                    after: after.clone(),
+                }),
+                Some(_) => {}
+            }
+        }
+        for after in &other.fields {
+            if self.field(after.name).is_none() {
+                changes.push(Change::Added(after.clone()));
+            }
+        }
+        changes
+    }
+}
+
+fn or_dash(value: Option<usize>) -> String {
+    value.map_or_else(|| "-".to_string(), |v| v.to_string())
+}
+
+/// One line per field: `offset size name: type`, with `-` where it depends on the value.
+impl fmt::Display for Schema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.fixed_size {
+            Some(size) => writeln!(f, "{} ({size} bytes)", self.name)?,
+            None => writeln!(f, "{} (variable size)", self.name)?,
+        }
+        for field in &self.fields {
+            writeln!(
+                f,
+                "{:>6} {:>6}  {}: {}",
+                or_dash(field.offset),
+                or_dash(field.size),
+                field.name,
+                field.ty
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Borsh, Json, Person, Serializer, Storage, Wincode};
+
+    #[storable]
+    #[derive(PartialEq, Debug)]
+    struct Vault {
+        owner: [u8; 32],
+        amount: u64,
+        bump: u8,
+    }
+
+    #[storable]
+    #[derive(PartialEq, Debug)]
+    struct VaultV2 {
+        owner: [u8; 32],
+        amount: u128,
+        bump: u8,
+        label: Option<String>,
+    }
+
+    fn vault() -> Vault {
+        Vault {
+            owner: [1; 32],
+            amount: 500,
+            bump: 254,
+        }
+    }
+
+    #[test]
+    fn fixed_layout_offsets() {
+        let schema = Vault::schema();
+        assert_eq!(schema.name, "Vault");
+        assert_eq!(schema.fixed_size, Some(41));
+        let offsets: Vec<_> = schema.fields.iter().map(|f| (f.name, f.ty, f.offset)).collect();
+        assert_eq!(
+            offsets,
+            vec![
+                ("owner", "[u8; 32]", Some(0)),
+                ("amount", "u64", Some(32)),
+                ("bump", "u8", Some(40)),
+            ]
+        );
+        assert_eq!(Borsh.to_bytes(&vault()).unwrap().len(), 41);
+        assert_eq!(<Vault as BorshSize>::FIXED_SIZE, Some(41));
+    }
+
+    #[test]
+    fn variable_fields_end_known_offsets() {
+        let schema = Person::schema();
+        assert_eq!(schema.fixed_size, None);
+        assert_eq!(schema.field("color_hex").unwrap().offset, Some(0));
+        assert_eq!(schema.field("color_hex").unwrap().size, None);
+        assert_eq!(schema.field("fav_num").unwrap().offset, None);
+        assert_eq!(
+            schema.to_string(),
+            "Person (variable size)\n     0      -  color_hex: String\n     -      8  fav_num: u64\n"
+        );
+    }
+
+    fn round_trip<S: Serializer<Vault>>(serializer: S) {
+        let bytes = serializer.to_bytes(&vault()).unwrap();
+        assert_eq!(serializer.from_bytes(&bytes).unwrap(), vault());
+    }
+
+    #[test]
+    fn storable_works_with_every_backend() {
+        round_trip(Borsh);
+        round_trip(Json);
+        round_trip(Wincode);
+        let mut st = Storage::new(Wincode);
+        st.save(&vault()).unwrap();
+        assert_eq!(st.convert(Json).unwrap().load().unwrap(), vault());
+    }
+
+    #[test]
+    fn diff_between_layouts() {
+        let mut changes = Vault::schema().diff(&VaultV2::schema());
+        assert_eq!(changes.len(), 3);
+        assert!(matches!(changes.pop(), Some(Change::Added(Field { name: "label", .. }))));
+        assert!(matches!(
+            &changes[0],
+            Change::Changed { before, after } if before.ty == "u64" && after.ty == "u128"
+        ));
+        assert!(matches!(
+            &changes[1],
+            Change::Changed { before, after } if before.offset == Some(40) && after.offset == Some(48)
+        ));
+        assert!(Vault::schema().diff(&Vault::schema()).is_empty());
+    }
+}
@@ -0,0 +1,239 @@
+//! Keyed collection of values encoded with any `Serializer`.
+//!
+//! Keys are stored as bytes whose lexicographic order matches the order of the
+//! key type, so iteration and range scans come back sorted by key.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Debug as db,
+    marker::PhantomData,
+    ops::{Bound, RangeBounds},
+};
+
+use crate::{Serializer, error::StorageError};
+
+/// Order-preserving byte encoding for store keys.
+pub trait Key: Sized {
+    fn to_key_bytes(&self) -> Vec<u8>;
+    fn from_key_bytes(bytes: &[u8]) -> Option<Self>;
+}
+
+macro_rules! unsigned_key {
+    ($($t:ty),*) => {$(
+        impl Key for $t {
+            fn to_key_bytes(&self) -> Vec<u8> {
+                self.to_be_bytes().to_vec()
+            }
+
+            fn from_key_bytes(bytes: &[u8]) -> Option<Self> {
+                Some(<$t>::from_be_bytes(bytes.try_into().ok()?))
+            }
+        }
+    )*};
+}
+
+// Flipping the sign bit makes negative numbers sort before positive ones.
+macro_rules! signed_key {
+    ($($t:ty => $u:ty),*) => {$(
+        impl Key for $t {
+            fn to_key_bytes(&self) -> Vec<u8> {
+                ((*self as $u) ^ (1 << (<$u>::BITS - 1))).to_be_bytes().to_vec()
+            }
+
+            fn from_key_bytes(bytes: &[u8]) -> Option<Self> {
+                let raw = <$u>::from_be_bytes(bytes.try_into().ok()?);
+                Some((raw ^ (1 << (<$u>::BITS - 1))) as $t)
+            }
+        }
+    )*};
+}
+
+unsigned_key!(u8, u16, u32, u64, u128);
+signed_key!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128);
+
+impl Key for String {
+    fn to_key_bytes(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+
+    fn from_key_bytes(bytes: &[u8]) -> Option<Self> {
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+impl Key for Vec<u8> {
+    fn to_key_bytes(&self) -> Vec<u8> {
+        self.clone()
+    }
+
+    fn from_key_bytes(bytes: &[u8]) -> Option<Self> {
+        Some(bytes.to_vec())
+    }
+}
+
+/// Fixed-size byte keys, e.g. 32-byte account addresses.
+impl<const N: usize> Key for [u8; N] {
+    fn to_key_bytes(&self) -> Vec<u8> {
+        self.to_vec()
+    }
+
+    fn from_key_bytes(bytes: &[u8]) -> Option<Self> {
+        bytes.try_into().ok()
+    }
+}
+
+pub struct Store<K, T, S> {
+    entries: BTreeMap<Vec<u8>, Vec<u8>>,
+    serializer: S,
+    _type: PhantomData<(K, T)>,
+}
+
+impl<K: Key, T: db, S: Serializer<T>> Store<K, T, S> {
+    pub fn new(serializer: S) -> Self {
+        Store {
+            entries: BTreeMap::new(),
+            serializer,
+            _type: PhantomData,
+        }
+    }
+
+    /// Inserts or replaces the value under `key`.
+    pub fn insert(&mut self, key: &K, value: &T) -> Result<(), StorageError> {
+        let bytes = self.serializer.to_bytes(value)?;
+        self.entries.insert(key.to_key_bytes(), bytes);
+        Ok(())
+    }
+
+    pub fn get(&self, key: &K) -> Result<Option<T>, StorageError> {
+        self.entries
+            .get(&key.to_key_bytes())
+            .map(|bytes| self.serializer.from_bytes(bytes))
+            .transpose()
+    }
+
+    pub fn remove(&mut self, key: &K) -> Result<Option<T>, StorageError> {
+        self.entries
+            .remove(&key.to_key_bytes())
+            .map(|bytes| self.serializer.from_bytes(&bytes))
+            .transpose()
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.entries.contains_key(&key.to_key_bytes())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// All entries in key order.
+    pub fn iter(&self) -> impl Iterator<Item = Result<(K, T), StorageError>> + '_ {
+        self.entries.iter().map(|(k, v)| self.decode(k, v))
+    }
+
+    /// Entries whose key falls in `range`, in key order. An inverted range is empty.
+    pub fn range<R: RangeBounds<K>>(
+        &self,
+        range: R,
+    ) -> impl Iterator<Item = Result<(K, T), StorageError>> + '_ {
+        let bounds: (Bound<Vec<u8>>, Bound<Vec<u8>>) = (
+            range.start_bound().map(Key::to_key_bytes),
+            range.end_bound().map(Key::to_key_bytes),
+        );
+        // `BTreeMap::range` panics on these instead of returning nothing.
+        let inverted = match &bounds {
+            (Bound::Excluded(start), Bound::Excluded(end)) => start >= end,
+            (Bound::Included(start) | Bound::Excluded(start), Bound::Included(end) | Bound::Excluded(end)) => {
+                start > end
+            }
+            _ => false,
+        };
+        (!inverted)
+            .then(|| self.entries.range(bounds))
+            .into_iter()
+            .flatten()
+            .map(|(k, v)| self.decode(k, v))
+    }
+
+    fn decode(&self, key: &[u8], value: &[u8]) -> Result<(K, T), StorageError> {
+        let key = K::from_key_bytes(key).ok_or_else(|| StorageError::UndecodableStoreKey(key.to_vec()))?;
+        Ok((key, self.serializer.from_bytes(value)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Borsh, Json, Person, Wincode};
+
+    fn person(fav_num: u64) -> Person {
+        Person {
+            color_hex: "ffffff".to_string(),
+            fav_num,
+        }
+    }
+
+    #[test]
+    fn insert_get_remove() {
+        let mut store = Store::new(Borsh);
+        assert!(store.is_empty());
+        store.insert(&"bob".to_string(), &person(1)).unwrap();
+        store.insert(&"alice".to_string(), &person(2)).unwrap();
+        store.insert(&"bob".to_string(), &person(3)).unwrap();
+
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.get(&"bob".to_string()).unwrap(), Some(person(3)));
+        assert_eq!(store.get(&"carol".to_string()).unwrap(), None);
+        assert_eq!(store.remove(&"alice".to_string()).unwrap(), Some(person(2)));
+        assert!(!store.contains_key(&"alice".to_string()));
+    }
+
+    #[test]
+    fn iterates_in_key_order() {
+        let mut store = Store::new(Wincode);
+        for key in [42i64, -7, 0, i64::MIN, 1_000] {
+            store.insert(&key, &person(key.unsigned_abs())).unwrap();
+        }
+        let keys: Vec<i64> = store.iter().map(|e| e.unwrap().0).collect();
+        assert_eq!(keys, vec![i64::MIN, -7, 0, 42, 1_000]);
+    }
+
+    #[test]
+    fn range_scans() {
+        let mut store = Store::new(Json);
+        for slot in [10u64, 20, 30, 40, 300] {
+            store.insert(&slot, &person(slot)).unwrap();
+        }
+        let hits: Vec<u64> = store.range(20..=40).map(|e| e.unwrap().1.fav_num).collect();
+        assert_eq!(hits, vec![20, 30, 40]);
+        let tail: Vec<u64> = store.range(35..).map(|e| e.unwrap().0).collect();
+        assert_eq!(tail, vec![40, 300]);
+        #[allow(clippy::reversed_empty_ranges)]
+        let inverted = store.range(40..20).count();
+        assert_eq!(inverted, 0);
+        assert_eq!(store.range((Bound::Excluded(20), Bound::Excluded(20))).count(), 0);
+    }
+
+    #[test]
+    fn undecodable_keys_are_reported() {
+        let mut store = Store::<String, Person, _>::new(Borsh);
+        store.entries.insert(vec![0xff], Borsh.to_bytes(&person(1)).unwrap());
+        assert!(matches!(
+            store.iter().next().unwrap(),
+            Err(StorageError::UndecodableStoreKey(bytes)) if bytes == [0xff]
+        ));
+    }
+
+    #[test]
+    fn byte_array_keys() {
+        let mut store = Store::new(Borsh);
+        store.insert(&[2u8; 32], &person(2)).unwrap();
+        store.insert(&[1u8; 32], &person(1)).unwrap();
+        let (first, _) = store.iter().next().unwrap().unwrap();
+        assert_eq!(first, [1u8; 32]);
+    }
+}
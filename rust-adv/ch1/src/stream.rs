@@ -0,0 +1,363 @@
+//! Many records on one `io::Write` / `io::Read`, each framed as `len u32 LE | payload`.
+//!
+//! Only one record is held in memory at a time, so large snapshots can be dumped
+//! to a file or socket and read back record by record.
+
+use std::{
+    fmt::Debug as db,
+    io::{self, Read, Write},
+    marker::PhantomData,
+};
+
+use wincode::io::{ReadResult, Reader, WriteResult, Writer, read_size_limit};
+
+use crate::{Serializer, error::StorageError};
+
+/// Frames longer than this are rejected on read unless raised with `with_max_frame_len`.
+pub const DEFAULT_MAX_FRAME_LEN: usize = 16 << 20;
+
+pub struct FrameWriter<W, S> {
+    writer: W,
+    serializer: S,
+    buf: Vec<u8>,
+}
+
+impl<W: Write, S> FrameWriter<W, S> {
+    pub fn new(writer: W, serializer: S) -> Self {
+        FrameWriter {
+            writer,
+            serializer,
+            buf: Vec::new(),
+        }
+    }
+
+    pub fn write<T: db>(&mut self, value: &T) -> Result<(), StorageError>
+    where
+        S: Serializer<T>,
+    {
+        self.buf.clear();
+        self.serializer.serialize_into(value, &mut self.buf)?;
+        let len = u32::try_from(self.buf.len()).map_err(|_| StorageError::TooLarge {
+            len: self.buf.len(),
+            max: u32::MAX as usize,
+        })?;
+        self.writer.write_all(&len.to_le_bytes())?;
+        self.writer.write_all(&self.buf)?;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+pub struct FrameReader<R, S, T> {
+    reader: R,
+    serializer: S,
+    buf: Vec<u8>,
+    max_frame_len: usize,
+    _type: PhantomData<T>,
+}
+
+impl<R: Read, S: Serializer<T>, T: db> FrameReader<R, S, T> {
+    pub fn new(reader: R, serializer: S) -> Self {
+        FrameReader {
+            reader,
+            serializer,
+            buf: Vec::new(),
+            max_frame_len: DEFAULT_MAX_FRAME_LEN,
+            _type: PhantomData,
+        }
+    }
+
+    pub fn with_max_frame_len(mut self, max_frame_len: usize) -> Self {
+        self.max_frame_len = max_frame_len;
+        self
+    }
+
+    /// Reads the next record, or `None` at a clean end of stream.
+    pub fn read(&mut self) -> Result<Option<T>, StorageError> {
+        let mut len = [0u8; 4];
+        let mut filled = 0;
+        while filled < len.len() {
+            match self.reader.read(&mut len[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => {
+                    return Err(StorageError::Truncated {
+                        expected: len.len(),
+                        actual: filled,
+                    });
+                }
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        let len = u32::from_le_bytes(len) as usize;
+        if len > self.max_frame_len {
+            return Err(StorageError::TooLarge {
+                len,
+                max: self.max_frame_len,
+            });
+        }
+        self.buf.clear();
+        let read = (&mut self.reader).take(len as u64).read_to_end(&mut self.buf)?;
+        if read < len {
+            return Err(StorageError::Truncated {
+                expected: len,
+                actual: read,
+            });
+        }
+        self.serializer.from_bytes(&self.buf).map(Some)
+    }
+}
+
+impl<R: Read, S: Serializer<T>, T: db> Iterator for FrameReader<R, S, T> {
+    type Item = Result<T, StorageError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read().transpose()
+    }
+}
+
+/// `wincode` reader over an `io::Read`. It pulls only the bytes the value asks for,
+/// so the stream is left right after the value.
+pub(crate) struct WincodeReader<R> {
+    reader: R,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: Read> WincodeReader<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        WincodeReader {
+            reader,
+            buf: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    /// Up to `n` unconsumed bytes, fewer only at end of stream.
+    fn fill(&mut self, n: usize) -> io::Result<&[u8]> {
+        let available = self.buf.len() - self.pos;
+        if available < n {
+            self.buf.drain(..self.pos);
+            self.pos = 0;
+            (&mut self.reader).take((n - available) as u64).read_to_end(&mut self.buf)?;
+        }
+        Ok(&self.buf[self.pos..self.buf.len().min(self.pos + n)])
+    }
+}
+
+impl<'a, R: Read> Reader<'a> for WincodeReader<R> {
+    type Trusted<'b>
+        = Window<'b>
+    where
+        Self: 'b;
+
+    fn fill_buf(&mut self, n_bytes: usize) -> ReadResult<&[u8]> {
+        Ok(self.fill(n_bytes)?)
+    }
+
+    unsafe fn consume_unchecked(&mut self, amt: usize) {
+        self.pos += amt;
+    }
+
+    fn consume(&mut self, amt: usize) -> ReadResult<()> {
+        self.fill_exact(amt)?;
+        self.pos += amt;
+        Ok(())
+    }
+
+    unsafe fn as_trusted_for(&mut self, n_bytes: usize) -> ReadResult<Window<'_>> {
+        self.fill_exact(n_bytes)?;
+        let start = self.pos;
+        self.pos += n_bytes;
+        Ok(Window(&self.buf[start..start + n_bytes]))
+    }
+}
+
+/// Bytes already pulled into a `WincodeReader`, handed out for a fixed-size read.
+pub(crate) struct Window<'b>(&'b [u8]);
+
+impl<'a> Reader<'a> for Window<'_> {
+    type Trusted<'c>
+        = Window<'c>
+    where
+        Self: 'c;
+
+    fn fill_buf(&mut self, n_bytes: usize) -> ReadResult<&[u8]> {
+        Ok(&self.0[..n_bytes.min(self.0.len())])
+    }
+
+    unsafe fn consume_unchecked(&mut self, amt: usize) {
+        self.0 = &self.0[amt..];
+    }
+
+    fn consume(&mut self, amt: usize) -> ReadResult<()> {
+        if self.0.len() < amt {
+            return Err(read_size_limit(amt));
+        }
+        self.0 = &self.0[amt..];
+        Ok(())
+    }
+
+    unsafe fn as_trusted_for(&mut self, n_bytes: usize) -> ReadResult<Window<'_>> {
+        if self.0.len() < n_bytes {
+            return Err(read_size_limit(n_bytes));
+        }
+        let (head, rest) = self.0.split_at(n_bytes);
+        self.0 = rest;
+        Ok(Window(head))
+    }
+}
+
+/// `wincode` writer over an `io::Write`.
+pub(crate) struct WincodeWriter<W>(pub(crate) W);
+
+impl<W: Write> Writer for WincodeWriter<W> {
+    type Trusted<'b>
+        = &'b mut Self
+    where
+        Self: 'b;
+
+    fn write(&mut self, src: &[u8]) -> WriteResult<()> {
+        self.0.write_all(src)?;
+        Ok(())
+    }
+
+    unsafe fn as_trusted_for(&mut self, _n_bytes: usize) -> WriteResult<&mut Self> {
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Borsh, Json, Person, Wincode};
+    use std::{
+        fs::File,
+        io::{BufReader, BufWriter},
+    };
+
+    fn people() -> Vec<Person> {
+        (0..3)
+            .map(|i| Person {
+                color_hex: format!("{i:06x}"),
+                fav_num: i,
+            })
+            .collect()
+    }
+
+    fn round_trip<S: Serializer<Person> + Copy>(serializer: S) {
+        let mut writer = FrameWriter::new(Vec::new(), serializer);
+        for p in people() {
+            writer.write(&p).unwrap();
+        }
+        let bytes = writer.into_inner();
+        let read: Vec<Person> = FrameReader::new(bytes.as_slice(), serializer)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(read, people());
+    }
+
+    #[test]
+    fn framed_round_trip_for_every_serializer() {
+        round_trip(Borsh);
+        round_trip(Wincode);
+        round_trip(Json);
+        #[cfg(feature = "bincode")]
+        round_trip(crate::formats::Bincode);
+        #[cfg(feature = "cbor")]
+        round_trip(crate::formats::Cbor);
+        #[cfg(feature = "msgpack")]
+        round_trip(crate::formats::MessagePack);
+        #[cfg(feature = "postcard")]
+        round_trip(crate::formats::Postcard);
+    }
+
+    #[test]
+    fn framed_file_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snapshot.bin");
+        let mut writer = FrameWriter::new(BufWriter::new(File::create(&path).unwrap()), Borsh);
+        for p in people() {
+            writer.write(&p).unwrap();
+        }
+        writer.flush().unwrap();
+        drop(writer);
+
+        let mut reader = FrameReader::new(BufReader::new(File::open(&path).unwrap()), Borsh);
+        assert_eq!(reader.read().unwrap(), Some(people().remove(0)));
+        assert_eq!(reader.count(), 2);
+    }
+
+    #[test]
+    fn truncated_and_oversized_frames_are_errors() {
+        let mut writer = FrameWriter::new(Vec::new(), Json);
+        writer.write(&people()[0]).unwrap();
+        let bytes = writer.into_inner();
+
+        let mut cut = FrameReader::<_, _, Person>::new(&bytes[..bytes.len() - 1], Json);
+        assert!(matches!(cut.read(), Err(StorageError::Truncated { .. })));
+        let mut half_len = FrameReader::<_, _, Person>::new(&bytes[..2], Json);
+        assert!(matches!(
+            half_len.read(),
+            Err(StorageError::Truncated {
+                expected: 4,
+                actual: 2
+            })
+        ));
+        let mut limited = FrameReader::<_, _, Person>::new(bytes.as_slice(), Json).with_max_frame_len(4);
+        assert!(matches!(limited.read(), Err(StorageError::TooLarge { max: 4, .. })));
+    }
+
+    #[test]
+    fn unframed_streaming() {
+        let p = people().remove(1);
+        let mut bytes = Vec::new();
+        Borsh.serialize_into(&p, &mut bytes).unwrap();
+        Borsh.serialize_into(&p, &mut bytes).unwrap();
+        let mut reader = bytes.as_slice();
+        let first: Person = Borsh.deserialize_from(&mut reader).unwrap();
+        assert_eq!(first, p);
+        assert_eq!(reader.len(), bytes.len() / 2);
+
+        let mut json = Vec::new();
+        Json.serialize_into(&p, &mut json).unwrap();
+        let decoded: Person = Json.deserialize_from(&mut json.as_slice()).unwrap();
+        assert_eq!(decoded, p);
+    }
+
+    fn back_to_back<S: Serializer<Person>>(serializer: S) {
+        let [a, b, ..]: [Person; 3] = people().try_into().unwrap();
+        let mut bytes = Vec::new();
+        serializer.serialize_into(&a, &mut bytes).unwrap();
+        serializer.serialize_into(&b, &mut bytes).unwrap();
+        assert_eq!(bytes, [serializer.to_bytes(&a).unwrap(), serializer.to_bytes(&b).unwrap()].concat());
+
+        // A chained reader hands out bytes in pieces, unlike a slice.
+        let (head, tail) = bytes.split_at(3);
+        let mut reader = head.chain(tail);
+        assert_eq!(serializer.deserialize_from(&mut reader).unwrap(), a);
+        assert_eq!(serializer.deserialize_from(&mut reader).unwrap(), b);
+        assert!(serializer.deserialize_from(&mut reader).is_err());
+    }
+
+    #[test]
+    fn reads_values_back_to_back() {
+        back_to_back(Borsh);
+        back_to_back(Wincode);
+        back_to_back(Json);
+        #[cfg(feature = "bincode")]
+        back_to_back(crate::formats::Bincode);
+        #[cfg(feature = "cbor")]
+        back_to_back(crate::formats::Cbor);
+        #[cfg(feature = "msgpack")]
+        back_to_back(crate::formats::MessagePack);
+    }
+}
@@ -0,0 +1,136 @@
+//! Reads that borrow from the input bytes instead of allocating.
+
+use std::fmt::Debug as db;
+
+use bytemuck::Pod as PodType;
+use serde::Deserialize;
+use wincode::{SchemaRead, config::DefaultConfig};
+
+use crate::{Json, Serializer, Wincode, envelope::Format, error::StorageError};
+
+/// Serializers that can decode a `T` whose references point into `bytes`,
+/// e.g. a `PersonRef<'a> { color_hex: &'a str, .. }` read from a `Person` payload.
+pub trait BorrowedSerializer<'a, T: 'a> {
+    #[allow(clippy::wrong_self_convention)]
+    fn from_bytes_borrowed(&self, bytes: &'a [u8]) -> Result<T, StorageError>;
+}
+
+/// Borrowed `&str` fields only work for strings without escape sequences;
+/// use `Cow<'a, str>` with `#[serde(borrow)]` to fall back to an allocation.
+impl<'a, T: Deserialize<'a> + 'a> BorrowedSerializer<'a, T> for Json {
+    fn from_bytes_borrowed(&self, bytes: &'a [u8]) -> Result<T, StorageError> {
+        serde_json::from_slice(bytes).map_err(|e| crate::json_error(Some(bytes), e))
+    }
+}
+
+impl<'a, T: SchemaRead<'a, DefaultConfig, Dst = T> + 'a> BorrowedSerializer<'a, T> for Wincode {
+    fn from_bytes_borrowed(&self, bytes: &'a [u8]) -> Result<T, StorageError> {
+        wincode::deserialize(bytes).map_err(|e| StorageError::decode("WinCode", None, e))
+    }
+}
+
+/// Raw in-memory layout of plain-old-data types, like Anchor zero-copy accounts.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Pod;
+
+impl<T: db + PodType> Serializer<T> for Pod {
+    fn to_bytes(&self, value: &T) -> Result<Vec<u8>, StorageError> {
+        Ok(bytemuck::bytes_of(value).to_vec())
+    }
+
+    fn from_bytes(&self, bytes: &[u8]) -> Result<T, StorageError> {
+        bytemuck::try_pod_read_unaligned(bytes)
+            .map_err(|e| StorageError::decode("Pod", None, format!("{e:?}")))
+    }
+
+    fn name(&self) -> &'static str {
+        "Pod"
+    }
+
+    fn format(&self) -> Format {
+        Format::Pod
+    }
+}
+
+/// Reinterprets `bytes` in place. Fails if they are not aligned for `T`.
+impl<'a, T: PodType> BorrowedSerializer<'a, &'a T> for Pod {
+    fn from_bytes_borrowed(&self, bytes: &'a [u8]) -> Result<&'a T, StorageError> {
+        bytemuck::try_from_bytes(bytes).map_err(|e| StorageError::decode("Pod", None, format!("{e:?}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Person, Storage, backend::AppendFile};
+    use bytemuck::Zeroable;
+
+    #[derive(Deserialize, SchemaRead, PartialEq, Debug)]
+    struct PersonRef<'a> {
+        color_hex: &'a str,
+        fav_num: u64,
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy, PartialEq, Debug, PodType, Zeroable)]
+    struct Quote {
+        slot: u64,
+        price: i64,
+    }
+
+    fn person() -> Person {
+        Person {
+            color_hex: "ffffff/000000".to_string(),
+            fav_num: 6,
+        }
+    }
+
+    fn points_into(s: &str, blob: &[u8]) -> bool {
+        blob.as_ptr_range().contains(&s.as_ptr())
+    }
+
+    #[test]
+    fn wincode_borrows_strings_from_the_blob() {
+        let mut st = Storage::new(Wincode);
+        st.save(&person()).unwrap();
+        let view: PersonRef = st.load_borrowed().unwrap();
+        assert_eq!(view.color_hex, "ffffff/000000");
+        assert_eq!(view.fav_num, 6);
+        assert!(points_into(view.color_hex, &st.blob().unwrap().unwrap()));
+    }
+
+    #[test]
+    fn json_borrows_strings_from_the_blob() {
+        let mut st = Storage::new(Json);
+        st.save(&person()).unwrap();
+        let view: PersonRef = st.load_borrowed().unwrap();
+        assert!(points_into(view.color_hex, &st.blob().unwrap().unwrap()));
+    }
+
+    #[test]
+    fn pod_round_trip_and_in_place_view() {
+        let quote = Quote { slot: 9, price: -42 };
+        let mut st = Storage::new(Pod);
+        st.save(&quote).unwrap();
+        assert_eq!(st.load().unwrap(), quote);
+
+        let bytes = Pod.to_bytes(&quote).unwrap();
+        let aligned: Vec<u64> = bytes
+            .chunks(8)
+            .map(|c| u64::from_ne_bytes(c.try_into().unwrap()))
+            .collect();
+        let view: &Quote = Pod
+            .from_bytes_borrowed(bytemuck::cast_slice(&aligned))
+            .unwrap();
+        assert_eq!(*view, quote);
+        assert!(<Pod as Serializer<Quote>>::from_bytes(&Pod, &bytes[1..]).is_err());
+    }
+
+    #[test]
+    fn borrowed_load_needs_a_borrowing_backend() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut st = Storage::with_backend(Wincode, AppendFile::new(dir.path().join("log")));
+        st.save(&person()).unwrap();
+        assert!(st.load_borrowed::<PersonRef>().is_err());
+    }
+}